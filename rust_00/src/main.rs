@@ -1,4 +1,97 @@
 use std::env;
+use std::io::{self, BufRead, Write};
+
+const LANGUAGES: &str = "en, es, fr, de, ja";
+
+fn greeting_for(lang: &str) -> Option<&'static str> {
+    match lang {
+        "en" => Some("Hello"),
+        "es" => Some("Hola"),
+        "fr" => Some("Bonjour"),
+        "de" => Some("Hallo"),
+        "ja" => Some("こんにちは"),
+        _ => None,
+    }
+}
+
+struct Opts {
+    upper: bool,
+    repeat: usize,
+    template: String,
+    greeting: &'static str,
+}
+
+fn greet(names: &[String], opts: &Opts) -> Vec<String> {
+    let mut lines = Vec::new();
+    for name in names {
+        for n in 1..=opts.repeat {
+            let msg = opts
+                .template
+                .replace("{n}", &n.to_string())
+                .replace("{greeting}", opts.greeting)
+                .replace("{name}", name);
+            if opts.upper {
+                lines.push(msg.to_uppercase());
+            } else {
+                lines.push(msg);
+            }
+        }
+    }
+    lines
+}
+
+// Terminal columns taken by `s`: East Asian wide characters (CJK, kana,
+// Hangul, fullwidth forms, most emoji) take two, everything else one.
+// Combining marks aren't special-cased.
+fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+// Boxes each message in a frame sized to the widest one, so names like "José"
+// and greetings like "こんにちは" line up.
+fn banner_lines(messages: &[String]) -> Vec<String> {
+    let width = messages.iter().map(|m| display_width(m)).max().unwrap_or(0);
+    let border = format!("+{}+", "-".repeat(width + 2));
+    let mut lines = Vec::new();
+    for msg in messages {
+        let pad = width - display_width(msg);
+        lines.push(border.clone());
+        lines.push(format!("| {}{} |", msg, " ".repeat(pad)));
+        lines.push(border.clone());
+    }
+    lines
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
 
 fn print_help() {
     println!(
@@ -7,7 +100,13 @@ Arguments:\n\
   [NAME] Name to greet [default: World]\n\
 Options:\n\
   --upper Convert to uppercase\n\
-  --repeat Repeat greeting N times [default: 1]\n\
+  --banner Draw an ASCII box around each greeting\n\
+  --repeat Repeat greeting N times, per name with --stdin [default: 1]\n\
+  --from Sign off the greetings with \"— NAME\"\n\
+  --jsonl Print each greeting as a JSON object on its own line\n\
+  --stdin Read names from stdin, one per line\n\
+  --template Greeting template, {{greeting}}, {{name}} and {{n}} are substituted [default: \"{{greeting}}, {{name}}!\"]\n\
+  --lang Greeting language: en, es, fr, de, ja [default: en]\n\
   -h, --help Print help"
     );
 }
@@ -15,7 +114,13 @@ Options:\n\
 fn main() {
     let mut name = String::from("World");
     let mut upper = false;
+    let mut banner = false;
+    let mut jsonl = false;
     let mut repeat: usize = 1;
+    let mut template = String::from("{greeting}, {name}!");
+    let mut greeting = "Hello";
+    let mut from_stdin = false;
+    let mut from: Option<String> = None;
 
     let mut args = env::args().skip(1).peekable();
 
@@ -26,6 +131,9 @@ fn main() {
                 return;
             }
             "--upper" => upper = true,
+            "--banner" => banner = true,
+            "--jsonl" => jsonl = true,
+            "--stdin" => from_stdin = true,
             "--repeat" => {
                 let val = match args.next() {
                     Some(v) => v,
@@ -43,6 +151,44 @@ fn main() {
                     }
                 };
             }
+            "--from" => {
+                from = match args.next() {
+                    Some(v) => Some(v),
+                    None => {
+                        eprintln!("error: Missing value for --from");
+                        eprintln!("error: Try '--help' for usage");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--template" => {
+                template = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --template");
+                        eprintln!("error: Try '--help' for usage");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--lang" => {
+                let code = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --lang");
+                        eprintln!("error: Try '--help' for usage");
+                        std::process::exit(2);
+                    }
+                };
+                greeting = match greeting_for(&code) {
+                    Some(g) => g,
+                    None => {
+                        eprintln!("error: Unsupported language: {}", code);
+                        eprintln!("error: Supported languages: {}", LANGUAGES);
+                        std::process::exit(2);
+                    }
+                };
+            }
             s if s.starts_with('-') => {
                 println!("error: Unknown option: {}", s);
                 println!("error: Try '--help' for usage");
@@ -52,11 +198,160 @@ fn main() {
         }
     }
 
-    let mut msg = format!("Hello, {}!", name);
-    if upper {
-        msg = msg.to_uppercase();
+    let names = if from_stdin {
+        let mut names = Vec::new();
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(l) if !l.trim().is_empty() => names.push(l.trim().to_string()),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("error: Failed to read stdin: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        names
+    } else {
+        vec![name]
+    };
+
+    let opts = Opts {
+        upper,
+        repeat,
+        template,
+        greeting,
+    };
+    let messages = greet(&names, &opts);
+    let sign_off = from.map(|f| {
+        let line = format!("— {}", f);
+        if upper {
+            line.to_uppercase()
+        } else {
+            line
+        }
+    });
+
+    if jsonl {
+        let mut out = io::stdout().lock();
+        for msg in &messages {
+            writeln!(out, "{{\"greeting\":\"{}\"}}", json_escape(msg))
+                .and_then(|_| out.flush())
+                .unwrap_or_else(|e| {
+                    eprintln!("error: Failed to write output: {}", e);
+                    std::process::exit(1);
+                });
+        }
+        if let Some(line) = &sign_off {
+            writeln!(out, "{{\"sign_off\":\"{}\"}}", json_escape(line)).unwrap_or_else(|e| {
+                eprintln!("error: Failed to write output: {}", e);
+                std::process::exit(1);
+            });
+        }
+    } else if banner {
+        for line in banner_lines(&messages) {
+            println!("{}", line);
+        }
+        if let Some(line) = &sign_off {
+            println!("{}", line);
+        }
+    } else {
+        for msg in &messages {
+            println!("{}", msg);
+        }
+        if let Some(line) = &sign_off {
+            println!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(template: &str, greeting: &'static str) -> Opts {
+        Opts {
+            upper: false,
+            repeat: 1,
+            template: template.to_string(),
+            greeting,
+        }
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn lang_picks_the_greeting() {
+        let greeting = greeting_for("fr").unwrap();
+        let lines = greet(&names(&["Ana"]), &opts("{greeting}, {name}!", greeting));
+        assert_eq!(lines, ["Bonjour, Ana!"]);
+        assert_eq!(greeting_for("ja"), Some("こんにちは"));
+        assert_eq!(greeting_for("xx"), None);
     }
-    for _ in 0..repeat {
-        println!("{}", msg);
+
+    #[test]
+    fn template_substitutes_every_placeholder() {
+        let mut o = opts("{n}. {greeting} {name}, {name}", "Hello");
+        o.repeat = 2;
+        let lines = greet(&names(&["Bo"]), &o);
+        assert_eq!(lines, ["1. Hello Bo, Bo", "2. Hello Bo, Bo"]);
+    }
+
+    #[test]
+    fn repeat_applies_to_each_name_in_order() {
+        let mut o = opts("{greeting} {name} #{n}", "Hello");
+        o.repeat = 2;
+        let lines = greet(&names(&["Ana", "Bo", "Cy"]), &o);
+        assert_eq!(
+            lines,
+            [
+                "Hello Ana #1",
+                "Hello Ana #2",
+                "Hello Bo #1",
+                "Hello Bo #2",
+                "Hello Cy #1",
+                "Hello Cy #2",
+            ]
+        );
+    }
+
+    #[test]
+    fn upper_applies_after_substitution() {
+        let mut o = opts("{greeting}, {name}!", "Hola");
+        o.upper = true;
+        assert_eq!(greet(&names(&["zoë"]), &o), ["HOLA, ZOË!"]);
+    }
+
+    #[test]
+    fn banner_counts_wide_characters_as_two_columns() {
+        let lines = banner_lines(&names(&["こんにちは", "Hi"]));
+        assert_eq!(
+            lines,
+            [
+                "+------------+",
+                "| こんにちは |",
+                "+------------+",
+                "+------------+",
+                "| Hi         |",
+                "+------------+",
+            ]
+        );
+    }
+
+    #[test]
+    fn banner_width_counts_chars_not_bytes() {
+        let lines = banner_lines(&names(&["Hi, José", "Hi, Al"]));
+        assert_eq!(
+            lines,
+            [
+                "+----------+",
+                "| Hi, José |",
+                "+----------+",
+                "+----------+",
+                "| Hi, Al   |",
+                "+----------+",
+            ]
+        );
     }
 }
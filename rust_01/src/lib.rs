@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+
+pub struct CountOptions {
+    pub ignore_case: bool,
+    pub min_length: usize,
+    pub min_alpha_ratio: f64,
+    pub stopwords: HashSet<String>,
+    // Treat stopwords as an allow-list: count only the listed words.
+    pub invert_stopwords: bool,
+    pub ignore_urls: bool,
+    pub split_hyphens: bool,
+    // Variant → canonical form, applied to each token after case folding.
+    pub synonyms: HashMap<String, String>,
+    pub tiebreak: Tiebreak,
+}
+
+impl Default for CountOptions {
+    fn default() -> Self {
+        CountOptions {
+            ignore_case: false,
+            min_length: 1,
+            min_alpha_ratio: 0.0,
+            stopwords: HashSet::new(),
+            invert_stopwords: false,
+            ignore_urls: false,
+            split_hyphens: false,
+            synonyms: HashMap::new(),
+            tiebreak: Tiebreak::Alpha,
+        }
+    }
+}
+
+pub fn count(input: &str, opts: &CountOptions) -> Vec<(String, usize)> {
+    let mut counts: WordCounts = HashMap::new();
+    count_words(input, &mut counts, opts);
+    top_words(&counts, counts.len(), opts.tiebreak)
+}
+
+// Best effort: only whole tokens with a URL prefix are caught, so a URL
+// broken up by whitespace still leaks its later pieces into the counts.
+fn looks_like_url(word: &str) -> bool {
+    ["http://", "https://", "www."].iter().any(|prefix| {
+        word.len() >= prefix.len()
+            && word.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+    })
+}
+
+// Each line lists a canonical label followed by the variants that should be
+// counted as it, e.g. `usa united-states america`.
+pub fn parse_synonyms(text: &str) -> HashMap<String, String> {
+    let mut synonyms = HashMap::new();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        let Some(canonical) = words.next() else {
+            continue;
+        };
+        for variant in words {
+            synonyms.insert(variant.to_string(), canonical.to_string());
+        }
+    }
+    synonyms
+}
+
+pub fn tokenize(input: &str, opts: &CountOptions) -> Vec<String> {
+    let mut words = Vec::new();
+    for token in input.split(|c: char| c.is_whitespace() || (opts.split_hyphens && c == '-')) {
+        if token.is_empty() {
+            continue;
+        }
+        let trimmed = token.trim_matches(|c: char| c != '\'' && c != '"' && !c.is_alphanumeric());
+        if trimmed.is_empty() {
+            continue;
+        }
+        let word = if opts.ignore_case {
+            trimmed.to_lowercase()
+        } else {
+            trimmed.to_string()
+        };
+        let word = match opts.synonyms.get(&word) {
+            Some(canonical) => canonical.clone(),
+            None => word,
+        };
+        let len = word.chars().count();
+        if len < opts.min_length {
+            continue;
+        }
+        let alpha = word.chars().filter(|c| c.is_alphabetic()).count();
+        if (alpha as f64) < opts.min_alpha_ratio * len as f64 {
+            continue;
+        }
+        if opts.ignore_urls && looks_like_url(&word) {
+            continue;
+        }
+        if opts.stopwords.contains(&word) != opts.invert_stopwords {
+            continue;
+        }
+        words.push(word);
+    }
+    words
+}
+
+pub type WordCounts = HashMap<String, (usize, usize)>;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tiebreak {
+    Alpha,
+    FirstSeen,
+}
+
+fn record(counts: &mut WordCounts, word: String) {
+    let next_index = counts.len();
+    counts.entry(word).or_insert((0, next_index)).0 += 1;
+}
+
+pub fn count_words(input: &str, counts: &mut WordCounts, opts: &CountOptions) {
+    for word in tokenize(input, opts) {
+        record(counts, word);
+    }
+}
+
+pub fn count_collocations(words: &[String], target: &str) -> WordCounts {
+    let mut neighbors: WordCounts = HashMap::new();
+    for (i, word) in words.iter().enumerate() {
+        if word != target {
+            continue;
+        }
+        if i > 0 {
+            record(&mut neighbors, words[i - 1].clone());
+        }
+        if let Some(next) = words.get(i + 1) {
+            record(&mut neighbors, next.clone());
+        }
+    }
+    neighbors
+}
+
+pub fn count_cooccurrences(words: &[String], window: usize) -> HashMap<(String, String), usize> {
+    let mut pairs: HashMap<(String, String), usize> = HashMap::new();
+    for (i, word) in words.iter().enumerate() {
+        for other in words.iter().skip(i + 1).take(window) {
+            if word == other {
+                continue;
+            }
+            let key = if word < other {
+                (word.clone(), other.clone())
+            } else {
+                (other.clone(), word.clone())
+            };
+            *pairs.entry(key).or_insert(0) += 1;
+        }
+    }
+    pairs
+}
+
+pub fn shannon_entropy(counts: &WordCounts) -> f64 {
+    let total: usize = counts.values().map(|(n, _)| n).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    counts
+        .values()
+        .map(|&(n, _)| {
+            let p = n as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// Token index (counted after filtering) and 1-based line number of every
+// occurrence of target.
+pub fn word_positions(input: &str, target: &str, opts: &CountOptions) -> Vec<(usize, usize)> {
+    let mut positions = Vec::new();
+    let mut index = 0;
+    for (line_no, line) in input.lines().enumerate() {
+        for word in tokenize(line, opts) {
+            if word == target {
+                positions.push((index, line_no + 1));
+            }
+            index += 1;
+        }
+    }
+    positions
+}
+
+pub const CLOUD_MIN_WEIGHT: f64 = 10.0;
+pub const CLOUD_MAX_WEIGHT: f64 = 100.0;
+
+// Font-size weights for word-cloud renderers: ln(count) scaled linearly so
+// the rarest kept word gets the minimum weight and the most common the
+// maximum. Output follows top_words ordering, which is weight-descending.
+pub fn cloud_weights(
+    counts: &WordCounts,
+    min_count: usize,
+    tiebreak: Tiebreak,
+) -> Vec<(String, u32)> {
+    let kept: Vec<(String, usize)> = top_words(counts, counts.len(), tiebreak)
+        .into_iter()
+        .filter(|(_, n)| *n >= min_count)
+        .collect();
+    let (lo, hi) = match (kept.last(), kept.first()) {
+        (Some(last), Some(first)) => ((last.1 as f64).ln(), (first.1 as f64).ln()),
+        _ => return Vec::new(),
+    };
+    kept.into_iter()
+        .map(|(word, n)| {
+            let scale = if hi > lo {
+                ((n as f64).ln() - lo) / (hi - lo)
+            } else {
+                1.0
+            };
+            let weight = CLOUD_MIN_WEIGHT + scale * (CLOUD_MAX_WEIGHT - CLOUD_MIN_WEIGHT);
+            (word, weight.round() as u32)
+        })
+        .collect()
+}
+
+pub fn case_variants(input: &str, opts: &CountOptions) -> Vec<(String, Vec<(String, usize)>)> {
+    let raw = CountOptions {
+        ignore_case: false,
+        min_length: opts.min_length,
+        min_alpha_ratio: opts.min_alpha_ratio,
+        stopwords: HashSet::new(),
+        invert_stopwords: false,
+        ignore_urls: opts.ignore_urls,
+        split_hyphens: opts.split_hyphens,
+        synonyms: HashMap::new(),
+        tiebreak: opts.tiebreak,
+    };
+    let mut groups: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    for word in tokenize(input, &raw) {
+        let key = word.to_lowercase();
+        let listed = opts.stopwords.contains(&key) || opts.stopwords.contains(&word);
+        if listed != opts.invert_stopwords {
+            continue;
+        }
+        *groups.entry(key).or_default().entry(word).or_insert(0) += 1;
+    }
+
+    let mut report: Vec<(String, Vec<(String, usize)>)> = groups
+        .into_iter()
+        .filter(|(_, variants)| variants.len() > 1)
+        .map(|(key, variants)| {
+            let mut variants: Vec<(String, usize)> = variants.into_iter().collect();
+            variants.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            (key, variants)
+        })
+        .collect();
+    report.sort_by(|a, b| a.0.cmp(&b.0));
+    report
+}
+
+pub fn dedupe_lines(input: &str) -> String {
+    let mut seen = HashSet::new();
+    let mut out = String::new();
+    for line in input.lines() {
+        if seen.insert(line) {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+pub fn count_lines(input: &str) -> usize {
+    let newlines = input.matches('\n').count();
+    if !input.is_empty() && !input.ends_with('\n') {
+        newlines + 1
+    } else {
+        newlines
+    }
+}
+
+pub fn count_sentences(input: &str) -> usize {
+    let chars: Vec<char> = input.chars().collect();
+    let mut count = 0;
+    for (i, c) in chars.iter().enumerate() {
+        if matches!(c, '.' | '!' | '?') {
+            match chars.get(i + 1) {
+                None => count += 1,
+                Some(next) if next.is_whitespace() => count += 1,
+                _ => {}
+            }
+        }
+    }
+    count
+}
+
+pub fn top_words(counts: &WordCounts, top_n: usize, tiebreak: Tiebreak) -> Vec<(String, usize)> {
+    let mut items: Vec<(&String, &(usize, usize))> = counts.iter().collect();
+    items.sort_by(|a, b| {
+        b.1 .0.cmp(&a.1 .0).then_with(|| match tiebreak {
+            Tiebreak::Alpha => a.0.cmp(b.0),
+            Tiebreak::FirstSeen => a.1 .1.cmp(&b.1 .1),
+        })
+    });
+    items
+        .into_iter()
+        .take(top_n)
+        .map(|(w, &(n, _))| (w.clone(), n))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_seen_breaks_ties_by_insertion_order() {
+        let opts = CountOptions {
+            tiebreak: Tiebreak::FirstSeen,
+            ..CountOptions::default()
+        };
+        let words = count("pear apple fig apple pear fig zucchini", &opts);
+        let expected = [("pear", 2), ("apple", 2), ("fig", 2), ("zucchini", 1)];
+        let expected: Vec<(String, usize)> =
+            expected.iter().map(|&(w, n)| (w.to_string(), n)).collect();
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn alpha_breaks_ties_alphabetically() {
+        let words = count("pear apple fig apple pear fig", &CountOptions::default());
+        let order: Vec<&str> = words.iter().map(|(w, _)| w.as_str()).collect();
+        assert_eq!(order, ["apple", "fig", "pear"]);
+    }
+
+    fn split(input: &str) -> Vec<String> {
+        let opts = CountOptions {
+            split_hyphens: true,
+            ..CountOptions::default()
+        };
+        tokenize(input, &opts)
+    }
+
+    #[test]
+    fn split_hyphens_breaks_compound_words() {
+        assert_eq!(split("well-known"), ["well", "known"]);
+        assert_eq!(split("state-of-the-art"), ["state", "of", "the", "art"]);
+    }
+
+    #[test]
+    fn split_hyphens_drops_leading_and_trailing_hyphens() {
+        assert_eq!(
+            split("-lead trail- --both-- - --"),
+            ["lead", "trail", "both"]
+        );
+    }
+
+    #[test]
+    fn hyphenated_words_stay_whole_by_default() {
+        let words = tokenize("well-known -lead", &CountOptions::default());
+        assert_eq!(words, ["well-known", "lead"]);
+    }
+}
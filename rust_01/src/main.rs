@@ -1,6 +1,14 @@
+use flate2::read::MultiGzDecoder;
+use rust_01::{
+    case_variants, cloud_weights, count_collocations, count_cooccurrences, count_lines,
+    count_sentences, count_words, dedupe_lines, parse_synonyms, shannon_entropy, tokenize,
+    top_words, word_positions, CountOptions, Tiebreak, WordCounts,
+};
 use std::collections::HashMap;
 use std::env;
-use std::io::{self, Read};
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, BufRead, Read};
 
 fn print_help() {
     println!(
@@ -9,9 +17,34 @@ Count word frequency in text\n\
 Arguments:\n\
   Text to analyze (or use stdin)\n\
 Options:\n\
+  --file Read text from FILE (gzip-compressed files are decompressed)\n\
   --top Show top N words [default: 10]\n\
   --min-length Ignore words shorter than N [default: 1]\n\
+  --min-alpha-ratio Ignore words whose share of letters is below F [default: 0.0]\n\
   --ignore-case Case insensitive counting\n\
+  --split-hyphens Count the parts of hyphenated words separately\n\
+  --tiebreak Order equal counts by alpha or first-seen [default: alpha]\n\
+  --tsv Print tab-separated word/count rows\n\
+  --json Print the word/count rows as a JSON array\n\
+  --pretty With --json, indent the array two spaces per level\n\
+  --max-output Stop at the last whole row within N bytes, ending with \"... (truncated)\"\n\
+  --output Write results to FILE instead of stdout\n\
+  --summary Also report total/unique words, lines and sentences\n\
+  --entropy Report the Shannon entropy of the word distribution in bits\n\
+  --dedupe-lines Drop repeated input lines (exact match, before --ignore-case)\n\
+  --stopwords Ignore words listed in FILE (whitespace separated)\n\
+  --synonyms Count the words on each line of FILE as the first one, its canonical label\n\
+  --exclude Ignore a comma-separated list of words (honors --ignore-case)\n\
+  --ignore-urls Skip tokens starting with http://, https:// or www. (best effort)\n\
+  --invert-stopwords Count only the --stopwords/--exclude words instead of skipping them\n\
+  --cooccurrence Count word pairs within N tokens of each other (work grows with N)\n\
+  --cloud Print every word with a 10-100 font weight from its log frequency\n\
+  --min-count With --cloud, skip words seen fewer than N times [default: 1]\n\
+  --case-report List words that appear with more than one capitalization\n\
+  --positions List the token indices and lines where WORD occurs\n\
+  --collocations Show the words most often adjacent to WORD\n\
+  --running-top Print the current top words every N lines read from stdin\n\
+  --repl Read stdin interactively, printing totals after each blank line\n\
   -h, --help"
     );
 }
@@ -35,16 +68,232 @@ fn format_number(n: usize) -> String {
     out
 }
 
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Compact output keeps everything on one line; pretty output breaks after
+// every opening bracket and comma and indents by the nesting depth.
+fn format_json(rows: &[(String, usize)], pretty: bool) -> String {
+    let break_line = |out: &mut String, depth: usize| {
+        if pretty {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth));
+        }
+    };
+    let sep = if pretty { ": " } else { ":" };
+    let mut out = String::from("[");
+    for (i, (w, n)) in rows.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        break_line(&mut out, 1);
+        out.push('{');
+        break_line(&mut out, 2);
+        let _ = write!(out, "\"word\"{}\"{}\",", sep, json_escape(w));
+        break_line(&mut out, 2);
+        let _ = write!(out, "\"count\"{}{}", sep, n);
+        break_line(&mut out, 1);
+        out.push('}');
+    }
+    if !rows.is_empty() {
+        break_line(&mut out, 0);
+    }
+    out.push(']');
+    out
+}
+
+// Collects the report for --max-output, stopping at the last whole row that
+// fits within `limit` bytes. Headers and the first row are always kept so a
+// tight limit still shows some data.
+struct BoundedOutput {
+    text: String,
+    limit: Option<usize>,
+    rows: usize,
+    truncated: bool,
+}
+
+impl BoundedOutput {
+    fn new(limit: Option<usize>) -> Self {
+        BoundedOutput {
+            text: String::new(),
+            limit,
+            rows: 0,
+            truncated: false,
+        }
+    }
+
+    fn fits(&mut self, len: usize) -> bool {
+        if !self.truncated
+            && self
+                .limit
+                .is_some_and(|limit| self.text.len() + len > limit)
+        {
+            self.truncated = true;
+        }
+        !self.truncated
+    }
+
+    fn header(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    fn line(&mut self, line: &str) {
+        if self.fits(line.len() + 1) {
+            self.header(line);
+        }
+    }
+
+    fn row(&mut self, line: &str) {
+        if self.rows == 0 || self.fits(line.len() + 1) {
+            self.header(line);
+            self.rows += 1;
+        }
+    }
+
+    // Rows sharing one line, separated by two spaces. Room for the closing
+    // newline is kept so `end_inline` never overshoots.
+    fn inline_row(&mut self, part: &str) {
+        let sep = if self.rows == 0 { "" } else { "  " };
+        if self.rows == 0 || self.fits(sep.len() + part.len() + 1) {
+            self.text.push_str(sep);
+            self.text.push_str(part);
+            self.rows += 1;
+        }
+    }
+
+    fn end_inline(&mut self) {
+        self.text.push('\n');
+    }
+
+    fn block(&mut self, text: &str, rows: usize) {
+        self.text.push_str(text);
+        self.rows += rows;
+    }
+
+    fn finish(mut self) -> (String, usize) {
+        if self.truncated {
+            self.text.push_str("... (truncated)\n");
+        }
+        (self.text, self.rows)
+    }
+}
+
 fn collect_text_from_stdin() -> io::Result<String> {
     let mut buf = String::new();
     io::stdin().read_to_string(&mut buf)?;
     Ok(buf)
 }
 
+fn read_input_file(path: &str) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut text = String::new();
+        MultiGzDecoder::new(&bytes[..]).read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+fn run_repl(top_n: usize, opts: &CountOptions) {
+    let mut counts: WordCounts = HashMap::new();
+    let mut pending = String::new();
+
+    let print_table = |counts: &WordCounts, label: &str| {
+        println!("{}", label);
+        for (w, n) in top_words(counts, top_n, opts.tiebreak) {
+            println!("{}: {}", w, format_number(n));
+        }
+    };
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("error: Failed to read stdin: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if line.trim().is_empty() {
+            count_words(&pending, &mut counts, opts);
+            pending.clear();
+            print_table(&counts, "Word frequency so far:");
+        } else {
+            pending.push_str(&line);
+            pending.push('\n');
+        }
+    }
+
+    count_words(&pending, &mut counts, opts);
+    print_table(&counts, "Final word frequency:");
+}
+
+fn run_running_top(interval: usize, top_n: usize, opts: &CountOptions) {
+    let mut counts: WordCounts = HashMap::new();
+    let mut lines_read = 0;
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("error: Failed to read stdin: {}", e);
+                std::process::exit(1);
+            }
+        };
+        count_words(&line, &mut counts, opts);
+        lines_read += 1;
+        if lines_read % interval == 0 {
+            println!("--- after {} lines ---", format_number(lines_read));
+            for (w, n) in top_words(&counts, top_n, opts.tiebreak) {
+                println!("{}: {}", w, format_number(n));
+            }
+        }
+    }
+
+    println!("--- final, {} lines ---", format_number(lines_read));
+    for (w, n) in top_words(&counts, top_n, opts.tiebreak) {
+        println!("{}: {}", w, format_number(n));
+    }
+}
+
 fn main() {
     let mut top_n: usize = 10;
-    let mut min_len: usize = 1;
-    let mut ignore_case = false;
+    let mut opts = CountOptions::default();
+    let mut stopword_list: Vec<String> = Vec::new();
+    let mut synonym_text = String::new();
+    let mut tsv = false;
+    let mut json = false;
+    let mut pretty = false;
+    let mut repl = false;
+    let mut running_top: Option<usize> = None;
+    let mut summary = false;
+    let mut entropy = false;
+    let mut dedupe = false;
+    let mut case_report = false;
+    let mut cloud = false;
+    let mut min_count: usize = 1;
+    let mut cooccurrence_window: Option<usize> = None;
+    let mut collocation_target: Option<String> = None;
+    let mut positions_target: Option<String> = None;
+    let mut output_path: Option<String> = None;
+    let mut max_output: Option<usize> = None;
+    let mut input_path: Option<String> = None;
 
     let mut args = env::args().skip(1).peekable();
 
@@ -56,7 +305,186 @@ fn main() {
                 print_help();
                 return;
             }
-            "--ignore-case" => ignore_case = true,
+            "--ignore-case" => opts.ignore_case = true,
+            "--tsv" => tsv = true,
+            "--json" => json = true,
+            "--pretty" => pretty = true,
+            "--repl" => repl = true,
+            "--stopwords" => {
+                let path = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --stopwords");
+                        std::process::exit(2);
+                    }
+                };
+                match fs::read_to_string(&path) {
+                    Ok(text) => stopword_list.extend(text.split_whitespace().map(String::from)),
+                    Err(e) => {
+                        eprintln!("error: Failed to read {}: {}", path, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--synonyms" => {
+                let path = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --synonyms");
+                        std::process::exit(2);
+                    }
+                };
+                match fs::read_to_string(&path) {
+                    Ok(text) => {
+                        synonym_text.push_str(&text);
+                        synonym_text.push('\n');
+                    }
+                    Err(e) => {
+                        eprintln!("error: Failed to read {}: {}", path, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--exclude" => {
+                let list = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --exclude");
+                        std::process::exit(2);
+                    }
+                };
+                stopword_list.extend(
+                    list.split(',')
+                        .map(|w| w.trim())
+                        .filter(|w| !w.is_empty())
+                        .map(String::from),
+                );
+            }
+            "--collocations" => {
+                collocation_target = match args.next() {
+                    Some(v) => Some(v),
+                    None => {
+                        eprintln!("error: Missing value for --collocations");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--positions" => {
+                positions_target = match args.next() {
+                    Some(v) => Some(v),
+                    None => {
+                        eprintln!("error: Missing value for --positions");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--invert-stopwords" => opts.invert_stopwords = true,
+            "--ignore-urls" => opts.ignore_urls = true,
+            "--split-hyphens" => opts.split_hyphens = true,
+            "--summary" => summary = true,
+            "--cloud" => cloud = true,
+            "--min-count" => {
+                let v = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --min-count");
+                        std::process::exit(2);
+                    }
+                };
+                min_count = match v.parse::<usize>() {
+                    Ok(n) if n > 0 => n,
+                    _ => {
+                        eprintln!("error: --min-count expects a positive integer");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--cooccurrence" => {
+                let v = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --cooccurrence");
+                        std::process::exit(2);
+                    }
+                };
+                cooccurrence_window = match v.parse::<usize>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => {
+                        eprintln!("error: --cooccurrence expects a positive integer");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--tiebreak" => {
+                opts.tiebreak = match args.next().as_deref() {
+                    Some("alpha") => Tiebreak::Alpha,
+                    Some("first-seen") => Tiebreak::FirstSeen,
+                    Some(other) => {
+                        eprintln!(
+                            "error: --tiebreak expects alpha or first-seen, got {}",
+                            other
+                        );
+                        std::process::exit(2);
+                    }
+                    None => {
+                        eprintln!("error: Missing value for --tiebreak");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--entropy" => entropy = true,
+            "--dedupe-lines" => dedupe = true,
+            "--case-report" => case_report = true,
+            "--file" => {
+                input_path = match args.next() {
+                    Some(v) => Some(v),
+                    None => {
+                        eprintln!("error: Missing value for --file");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--max-output" => {
+                let v = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --max-output");
+                        std::process::exit(2);
+                    }
+                };
+                max_output = match v.parse::<usize>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => {
+                        eprintln!("error: --max-output expects a positive number of bytes");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--output" => {
+                output_path = match args.next() {
+                    Some(v) => Some(v),
+                    None => {
+                        eprintln!("error: Missing value for --output");
+                        std::process::exit(2);
+                    }
+                };
+            }
+            "--running-top" => {
+                let v = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --running-top");
+                        std::process::exit(2);
+                    }
+                };
+                running_top = match v.parse::<usize>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => {
+                        eprintln!("error: --running-top expects a positive number of lines");
+                        std::process::exit(2);
+                    }
+                };
+            }
             "--top" => {
                 let v = match args.next() {
                     Some(v) => v,
@@ -81,7 +509,7 @@ fn main() {
                         std::process::exit(2);
                     }
                 };
-                min_len = match v.parse::<usize>() {
+                opts.min_length = match v.parse::<usize>() {
                     Ok(n) if n > 0 => n,
                     _ => {
                         eprintln!("error: --min-length expects a positive integer");
@@ -89,6 +517,22 @@ fn main() {
                     }
                 };
             }
+            "--min-alpha-ratio" => {
+                let v = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        eprintln!("error: Missing value for --min-alpha-ratio");
+                        std::process::exit(2);
+                    }
+                };
+                opts.min_alpha_ratio = match v.parse::<f64>() {
+                    Ok(f) if (0.0..=1.0).contains(&f) => f,
+                    _ => {
+                        eprintln!("error: --min-alpha-ratio expects a number between 0 and 1");
+                        std::process::exit(2);
+                    }
+                };
+            }
             s if s.starts_with('-') => {
                 println!("error: Unknown option: {}", s);
                 println!("error: Try '--help' for usage");
@@ -98,9 +542,77 @@ fn main() {
         }
     }
 
-    let from_stdin = text_parts.is_empty();
+    if pretty && !json {
+        eprintln!("error: --pretty only applies to --json output");
+        std::process::exit(2);
+    }
+    if json
+        && (tsv
+            || repl
+            || running_top.is_some()
+            || cloud
+            || case_report
+            || summary
+            || entropy
+            || cooccurrence_window.is_some())
+    {
+        eprintln!("error: --json only formats the word/count table");
+        std::process::exit(2);
+    }
+
+    if opts.invert_stopwords && stopword_list.is_empty() {
+        eprintln!("error: --invert-stopwords needs a word list from --stopwords or --exclude");
+        std::process::exit(2);
+    }
+
+    opts.stopwords = stopword_list
+        .into_iter()
+        .map(|w| {
+            if opts.ignore_case {
+                w.to_lowercase()
+            } else {
+                w
+            }
+        })
+        .collect();
+
+    if opts.ignore_case {
+        synonym_text = synonym_text.to_lowercase();
+    }
+    opts.synonyms = parse_synonyms(&synonym_text);
 
-    let input = if from_stdin {
+    if max_output.is_some() && (repl || running_top.is_some()) {
+        eprintln!("error: --max-output does not apply to --repl or --running-top");
+        std::process::exit(2);
+    }
+
+    if repl {
+        run_repl(top_n, &opts);
+        return;
+    }
+
+    if let Some(interval) = running_top {
+        if !text_parts.is_empty() || input_path.is_some() {
+            eprintln!(
+                "error: --running-top reads stdin and cannot be combined with text or --file"
+            );
+            std::process::exit(2);
+        }
+        run_running_top(interval, top_n, &opts);
+        return;
+    }
+
+    let from_stdin = text_parts.is_empty() && input_path.is_none();
+
+    let raw_input = if let Some(path) = &input_path {
+        match read_input_file(path) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("error: Failed to read {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    } else if from_stdin {
         match collect_text_from_stdin() {
             Ok(t) => t,
             Err(e) => {
@@ -112,45 +624,166 @@ fn main() {
         text_parts.join(" ")
     };
 
-    let mut counts: HashMap<String, usize> = HashMap::new();
-    for token in input.split(|c: char| c.is_whitespace()) {
-        if token.is_empty() {
-            continue;
-        }
-        let trimmed = token.trim_matches(|c: char| c != '\'' && c != '"' && !c.is_alphanumeric());
-        if trimmed.is_empty() {
-            continue;
-        }
-        let word = if ignore_case {
-            trimmed.to_lowercase()
+    // Deduping only affects the counts; positions and line totals refer to
+    // the text as given.
+    let deduped = dedupe.then(|| dedupe_lines(&raw_input));
+    let input = deduped.as_deref().unwrap_or(&raw_input);
+
+    if let Some(target) = &positions_target {
+        let target = if opts.ignore_case {
+            target.to_lowercase()
         } else {
-            trimmed.to_string()
+            target.clone()
         };
-        if word.chars().count() < min_len {
-            continue;
+        let positions = word_positions(&raw_input, &target, &opts);
+        if positions.is_empty() {
+            println!("\"{}\" does not appear", target);
+            std::process::exit(1);
         }
-        *counts.entry(word).or_insert(0) += 1;
+        let tokens: Vec<String> = positions.iter().map(|(i, _)| i.to_string()).collect();
+        let mut lines: Vec<String> = positions.iter().map(|(_, l)| l.to_string()).collect();
+        lines.dedup();
+        println!("Positions of \"{}\": {}", target, tokens.join(", "));
+        println!("Lines: {}", lines.join(", "));
+        return;
     }
 
-    let mut items: Vec<(String, usize)> = counts.into_iter().collect();
-    items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut counts: WordCounts = HashMap::new();
+    count_words(input, &mut counts, &opts);
 
-    let to_show: Vec<_> = items.into_iter().take(top_n).collect();
+    let to_show = match &collocation_target {
+        Some(target) => {
+            let target = if opts.ignore_case {
+                target.to_lowercase()
+            } else {
+                target.clone()
+            };
+            let words = tokenize(input, &opts);
+            top_words(&count_collocations(&words, &target), top_n, opts.tiebreak)
+        }
+        None => top_words(&counts, top_n, opts.tiebreak),
+    };
 
-    if from_stdin {
-        let parts: Vec<String> = to_show
-            .iter()
-            .map(|(w, n)| format!("{}: {}", w, n))
-            .collect();
-        println!("{}", parts.join("  "));
+    let mut out = BoundedOutput::new(max_output);
+    if let Some(window) = cooccurrence_window {
+        let words = tokenize(input, &opts);
+        let mut pairs: Vec<((String, String), usize)> =
+            count_cooccurrences(&words, window).into_iter().collect();
+        pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        pairs.truncate(top_n);
+        for ((a, b), n) in &pairs {
+            if tsv {
+                out.row(&format!("{}\t{}\t{}", a, b, n));
+            } else {
+                out.row(&format!("{} + {}: {}", a, b, format_number(*n)));
+            }
+        }
+    } else if cloud {
+        for (w, weight) in cloud_weights(&counts, min_count, opts.tiebreak) {
+            if tsv {
+                out.row(&format!("{}\t{}", w, weight));
+            } else {
+                out.row(&format!("{} {}", w, weight));
+            }
+        }
+    } else if case_report {
+        let report = case_variants(input, &opts);
+        if report.is_empty() {
+            out.header("No mixed-case words found");
+        } else {
+            out.header("Case variants:");
+        }
+        for (word, variants) in &report {
+            let parts: Vec<String> = variants
+                .iter()
+                .map(|(v, n)| format!("{} ({})", v, format_number(*n)))
+                .collect();
+            out.row(&format!("  {}: {}", word, parts.join(", ")));
+        }
+    } else if json {
+        // Truncating the text would leave invalid JSON, so drop whole rows
+        // instead and note the cut on stderr.
+        let fits = |rows: usize| {
+            max_output.is_none_or(|limit| format_json(&to_show[..rows], pretty).len() < limit)
+        };
+        let mut rows = to_show.len();
+        if !fits(rows) {
+            let (mut low, mut high) = (0, rows);
+            while low < high {
+                let mid = (low + high).div_ceil(2);
+                if fits(mid) {
+                    low = mid;
+                } else {
+                    high = mid - 1;
+                }
+            }
+            rows = low;
+            eprintln!(
+                "note: JSON truncated to {} of {} rows by --max-output",
+                rows,
+                to_show.len()
+            );
+        }
+        out.block(
+            &format!("{}\n", format_json(&to_show[..rows], pretty)),
+            rows,
+        );
+    } else if tsv {
+        for (w, n) in &to_show {
+            out.row(&format!("{}\t{}", w, n));
+        }
+    } else if from_stdin {
+        for (w, n) in &to_show {
+            out.inline_row(&format!("{}: {}", w, n));
+        }
+        out.end_inline();
     } else {
-        if top_n == 10 {
-            println!("Word frequency:");
+        if let Some(target) = &collocation_target {
+            out.header(&format!("Words next to \"{}\":", target));
+        } else if top_n == 10 {
+            out.header("Word frequency:");
         } else {
-            println!("Top {} words:", top_n);
+            out.header(&format!("Top {} words:", top_n));
         }
-        for (w, n) in to_show {
-            println!("{}: {}", w, format_number(n));
+        for (w, n) in &to_show {
+            out.row(&format!("{}: {}", w, format_number(*n)));
+        }
+    }
+
+    if summary {
+        out.line("Summary:");
+        out.line(&format!(
+            "  Total words: {}",
+            format_number(counts.values().map(|(n, _)| n).sum())
+        ));
+        out.line(&format!("  Unique words: {}", format_number(counts.len())));
+        out.line(&format!(
+            "  Lines: {}",
+            format_number(count_lines(&raw_input))
+        ));
+        out.line(&format!(
+            "  Sentences: {}",
+            format_number(count_sentences(&raw_input))
+        ));
+    }
+
+    if entropy {
+        if counts.is_empty() {
+            out.line("Entropy: 0.0 bits");
+        } else {
+            out.line(&format!("Entropy: {:.3} bits", shannon_entropy(&counts)));
+        }
+    }
+
+    let (out, rows_written) = out.finish();
+    match output_path {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, &out) {
+                eprintln!("error: Failed to write {}: {}", path, e);
+                std::process::exit(1);
+            }
+            eprintln!("wrote {} rows to {}", rows_written, path);
         }
+        None => print!("{}", out),
     }
 }
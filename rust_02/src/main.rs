@@ -1,8 +1,10 @@
 use std::env;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
 use std::process;
 
+const DIFF_DISPLAY_LIMIT: usize = 64;
+
 fn print_help() {
     println!("Usage: hextool [OPTIONS]");
     println!("\\nRead and write binary files in hexadecimal");
@@ -10,8 +12,39 @@ fn print_help() {
     println!("  -f, --file    Target file");
     println!("  -r, --read    Read mode (display hex)");
     println!("  -w, --write   Write mode (hex string to write)");
+    println!("      --write-text <s>  Write the UTF-8 bytes of a string");
     println!("  -o, --offset  Offset in bytes (decimal or 0x hex)");
     println!("  -s, --size    Number of bytes to read");
+    println!("      --swap    Swap byte order in 2, 4 or 8 byte groups");
+    println!("      --xor <key>  XOR the range with a repeating hex key");
+    println!("      --repeat-write <hex>  Tile a hex pattern over --size bytes at the offset");
+    println!("      --flip-bit <0-7>  Toggle one bit of the byte at the offset");
+    println!("      --after <hex>  With --write, write right after the first match of a signature");
+    println!("      --interactive  Confirm before modifying an existing file");
+    println!("      --dry-run Preview changes without writing");
+    println!("      --stdin   Read data from stdin instead of a file");
+    println!("      --dump-to <path>  Write the read dump to a file instead of stdout");
+    println!("      --offset-width <n>  Digits in the dump offset column [default: 8]");
+    println!("      --canonical Format reads exactly like `hexdump -C`");
+    println!("      --annotate  Identify known file magic bytes after a read");
+    println!("      --pattern-scan  Report runs of a repeated byte in the range");
+    println!("      --byte-histogram  Count how often each byte value appears in the range");
+    println!("      --by-count  Sort --byte-histogram by frequency instead of byte value");
+    println!("      --bars      Draw a bar next to each --byte-histogram row");
+    println!("      --strings   List printable ASCII runs in the range, like strings(1)");
+    println!("      --strings-stats  With --strings, report the count and min/max/average length");
+    println!(
+        "      --min-run   Minimum run length [default: 16 for --pattern-scan, 4 for --strings]"
+    );
+    println!("      --truncate <n>  Set the file length to n bytes");
+    println!("      --diff-range <a> <b>  Compare two files over --offset/--size");
+    println!("      --align <n>  In diff modes, compare file b starting n bytes later");
+    println!("      --visual-diff <a> <b>  Show two files side by side, marking differing bytes");
+    println!("      --no-color  Mark --visual-diff differences with brackets instead of red");
+    println!("      --crc-compare <a> <b>  Compare the CRC-32 of two files");
+    println!(
+        "      --verify-list <list>  Check the CRC-32 of each `offset size crc` region in list"
+    );
     println!("  -h, --help    Print help");
 }
 
@@ -35,14 +68,286 @@ fn hex_string_to_bytes(s: &str) -> Result<Vec<u8>, String> {
         .collect()
 }
 
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xEDB88320;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    crc
+}
+
+fn crc32_file(path: &str) -> Result<u32, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut buffer = [0u8; 64 * 1024];
+    let mut crc = 0xFFFFFFFF;
+    loop {
+        let n = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if n == 0 {
+            break;
+        }
+        crc = crc32_update(crc, &buffer[..n]);
+    }
+    Ok(!crc)
+}
+
+// One region per line: offset (decimal or 0x hex), size, expected CRC-32 in
+// hex. Blank lines and lines starting with '#' are skipped by the caller.
+fn parse_verify_line(line: &str) -> Result<(u64, usize, u32), String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 3 {
+        return Err("expected `offset size crc`".to_string());
+    }
+    let size = match fields[1].strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => fields[1].parse(),
+    }
+    .map_err(|_| format!("invalid size '{}'", fields[1]))?;
+    let crc_str = fields[2].strip_prefix("0x").unwrap_or(fields[2]);
+    let crc =
+        u32::from_str_radix(crc_str, 16).map_err(|_| format!("invalid CRC '{}'", fields[2]))?;
+    Ok((parse_offset(fields[0]), size, crc))
+}
+
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (
+        &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A],
+        "PNG image",
+    ),
+    (&[0x7F, b'E', b'L', b'F'], "ELF executable"),
+    (b"%PDF", "PDF document"),
+    (&[b'P', b'K', 0x03, 0x04], "ZIP archive"),
+    (&[0x1F, 0x8B], "GZIP compressed data"),
+];
+
+fn detect_format(data: &[u8]) -> &'static str {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(prefix, _)| data.starts_with(prefix))
+        .map(|(_, label)| *label)
+        .unwrap_or("unknown format")
+}
+
+// Ok(None) means the offset lies past the end of the file.
+fn read_range(path: &str, offset: u64, size: Option<usize>) -> io::Result<Option<Vec<u8>>> {
+    let mut file = File::open(path)?;
+
+    let file_len = file.metadata()?.len();
+    if offset > file_len {
+        return Ok(None);
+    }
+
+    file.seek(SeekFrom::Start(offset))?;
+
+    let bytes_to_read = match size {
+        Some(s) => s as u64,
+        None => file_len - offset,
+    };
+
+    let mut buffer = Vec::new();
+    file.take(bytes_to_read).read_to_end(&mut buffer)?;
+    Ok(Some(buffer))
+}
+
+// For modes that treat a range past the end of the file as empty.
+fn read_range_or_exit(path: &str, offset: u64, size: Option<usize>) -> Vec<u8> {
+    match read_range(path, offset, size) {
+        Ok(data) => data.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("error: Failed to read {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+// Asks on stderr and exits without touching the file unless the answer is yes.
+fn confirm(question: &str) {
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush().expect("Failed to flush stderr");
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .expect("Failed to read answer");
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        eprintln!("Aborted, nothing written");
+        process::exit(1);
+    }
+}
+
+fn find_runs(data: &[u8], min_run: usize) -> Vec<(usize, u8, usize)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=data.len() {
+        if i == data.len() || data[i] != data[start] {
+            let len = i - start;
+            if len >= min_run {
+                runs.push((start, data[start], len));
+            }
+            start = i;
+        }
+    }
+    runs
+}
+
+fn byte_histogram(data: &[u8]) -> [u64; 256] {
+    let mut counts = [0u64; 256];
+    for &b in data {
+        counts[b as usize] += 1;
+    }
+    counts
+}
+
+const HISTOGRAM_BAR_WIDTH: u64 = 40;
+
+fn find_strings(data: &[u8], min_len: usize) -> Vec<(usize, String)> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    for i in 0..=data.len() {
+        let printable = i < data.len() && (0x20..=0x7E).contains(&data[i]);
+        if printable {
+            continue;
+        }
+        if i - start >= min_len {
+            found.push((start, String::from_utf8_lossy(&data[start..i]).into_owned()));
+        }
+        start = i + 1;
+    }
+    found
+}
+
+const VISUAL_DIFF_WIDTH: usize = 8;
+
+fn format_diff_cell(byte: Option<&u8>, differs: bool, color: bool) -> String {
+    let hex = match byte {
+        Some(b) => format!("{:02x}", b),
+        None => "--".to_string(),
+    };
+    match (differs, color) {
+        (false, _) => format!(" {} ", hex),
+        (true, true) => format!(" \x1b[31m{}\x1b[0m ", hex),
+        (true, false) => format!("[{}]", hex),
+    }
+}
+
+fn find_pattern(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+fn print_hex_dump(out: &mut impl Write, data: &[u8], offset: u64, width: usize) -> io::Result<()> {
+    for (line_idx, chunk) in data.chunks(16).enumerate() {
+        let current_offset = offset + (line_idx * 16) as u64;
+
+        write!(out, "{:0width$x}:", current_offset, width = width)?;
+
+        for byte in chunk {
+            write!(out, " {:02x}", byte)?;
+        }
+
+        if chunk.len() < 16 {
+            for _ in 0..(16 - chunk.len()) {
+                write!(out, "   ")?;
+            }
+        }
+
+        write!(out, " |")?;
+
+        for byte in chunk {
+            if *byte >= 0x20 && *byte <= 0x7E {
+                write!(out, "{}", *byte as char)?;
+            } else {
+                write!(out, ".")?;
+            }
+        }
+        writeln!(out, "|")?;
+    }
+    Ok(())
+}
+
+fn print_canonical_dump(out: &mut impl Write, data: &[u8], offset: u64) -> io::Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let mut previous: Option<&[u8]> = None;
+    let mut squeezing = false;
+    for (line_idx, chunk) in data.chunks(16).enumerate() {
+        if chunk.len() == 16 && previous == Some(chunk) {
+            if !squeezing {
+                writeln!(out, "*")?;
+                squeezing = true;
+            }
+            continue;
+        }
+        previous = Some(chunk);
+        squeezing = false;
+
+        let mut line = format!("{:08x}  ", offset + (line_idx * 16) as u64);
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(b) => line.push_str(&format!("{:02x} ", b)),
+                None => line.push_str("   "),
+            }
+            if i == 7 {
+                line.push(' ');
+            }
+        }
+        line.push_str(" |");
+        for byte in chunk {
+            if *byte >= 0x20 && *byte <= 0x7E {
+                line.push(*byte as char);
+            } else {
+                line.push('.');
+            }
+        }
+        line.push('|');
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "{:08x}", offset + data.len() as u64)
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let mut file_path: Option<String> = None;
     let mut read_mode = false;
     let mut write_data: Option<String> = None;
+    let mut write_text: Option<String> = None;
     let mut offset: u64 = 0;
     let mut size: Option<usize> = None;
+    let mut swap_width: Option<usize> = None;
+    let mut dry_run = false;
+    let mut interactive = false;
+    let mut after_sig: Option<String> = None;
+    let mut use_stdin = false;
+    let mut annotate = false;
+    let mut canonical = false;
+    let mut offset_width: usize = 8;
+    let mut dump_to: Option<String> = None;
+    let mut pattern_scan = false;
+    let mut min_run: Option<usize> = None;
+    let mut strings_mode = false;
+    let mut strings_stats = false;
+    let mut histogram = false;
+    let mut by_count = false;
+    let mut bars = false;
+    let mut offset_given = false;
+    let mut crc_files: Option<(String, String)> = None;
+    let mut verify_list: Option<String> = None;
+    let mut diff_files: Option<(String, String)> = None;
+    let mut visual_diff_files: Option<(String, String)> = None;
+    let mut align: u64 = 0;
+    let mut no_color = false;
+    let mut truncate_len: Option<u64> = None;
+    let mut xor_key: Option<String> = None;
+    let mut repeat_pattern: Option<String> = None;
+    let mut flip_bit: Option<u8> = None;
 
     let mut i = 1;
     if args.len() == 1 {
@@ -76,9 +381,19 @@ fn main() {
                     i += 1;
                 }
             }
+            "--write-text" => {
+                if i + 1 < args.len() {
+                    write_text = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --write-text requires a string");
+                    process::exit(2);
+                }
+            }
             "-o" | "--offset" => {
                 if i + 1 < args.len() {
                     offset = parse_offset(&args[i + 1]);
+                    offset_given = true;
                     i += 2;
                 } else {
                     i += 1;
@@ -92,6 +407,215 @@ fn main() {
                     i += 1;
                 }
             }
+            "--swap" => {
+                if i + 1 < args.len() {
+                    swap_width = match args[i + 1].as_str() {
+                        "2" => Some(2),
+                        "4" => Some(4),
+                        "8" => Some(8),
+                        _ => {
+                            eprintln!("error: --swap expects 2, 4 or 8");
+                            process::exit(2);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+            }
+            "--xor" => {
+                if i + 1 < args.len() {
+                    xor_key = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --xor requires a hex key");
+                    process::exit(2);
+                }
+            }
+            "--flip-bit" => {
+                if i + 1 < args.len() {
+                    flip_bit = match args[i + 1].parse::<u8>() {
+                        Ok(b) if b < 8 => Some(b),
+                        _ => {
+                            eprintln!("error: --flip-bit must be a bit index from 0 to 7");
+                            process::exit(2);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    eprintln!("error: --flip-bit requires a bit index");
+                    process::exit(2);
+                }
+            }
+            "--repeat-write" => {
+                if i + 1 < args.len() {
+                    repeat_pattern = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --repeat-write requires a hex pattern");
+                    process::exit(2);
+                }
+            }
+            "--diff-range" => {
+                if i + 2 < args.len() {
+                    diff_files = Some((args[i + 1].clone(), args[i + 2].clone()));
+                    i += 3;
+                } else {
+                    eprintln!("error: --diff-range requires two files");
+                    process::exit(2);
+                }
+            }
+            "--visual-diff" => {
+                if i + 2 < args.len() {
+                    visual_diff_files = Some((args[i + 1].clone(), args[i + 2].clone()));
+                    i += 3;
+                } else {
+                    eprintln!("error: --visual-diff requires two files");
+                    process::exit(2);
+                }
+            }
+            "--align" => {
+                if i + 1 < args.len() {
+                    align = parse_offset(&args[i + 1]);
+                    i += 2;
+                } else {
+                    eprintln!("error: --align requires a byte count");
+                    process::exit(2);
+                }
+            }
+            "--no-color" => {
+                no_color = true;
+                i += 1;
+            }
+            "--crc-compare" => {
+                if i + 2 < args.len() {
+                    crc_files = Some((args[i + 1].clone(), args[i + 2].clone()));
+                    i += 3;
+                } else {
+                    eprintln!("error: --crc-compare requires two files");
+                    process::exit(2);
+                }
+            }
+            "--verify-list" => {
+                if i + 1 < args.len() {
+                    verify_list = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --verify-list requires a file");
+                    process::exit(2);
+                }
+            }
+            "--truncate" => {
+                if i + 1 < args.len() {
+                    let value = &args[i + 1];
+                    let parsed = match value.strip_prefix("0x") {
+                        Some(hex) => u64::from_str_radix(hex, 16),
+                        None => value.parse(),
+                    };
+                    truncate_len = match parsed {
+                        Ok(n) => Some(n),
+                        Err(_) => {
+                            eprintln!("error: --truncate expects a byte count");
+                            process::exit(2);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    eprintln!("error: --truncate requires a length");
+                    process::exit(2);
+                }
+            }
+            "--strings" => {
+                strings_mode = true;
+                i += 1;
+            }
+            "--strings-stats" => {
+                strings_mode = true;
+                strings_stats = true;
+                i += 1;
+            }
+            "--byte-histogram" => {
+                histogram = true;
+                i += 1;
+            }
+            "--by-count" => {
+                by_count = true;
+                i += 1;
+            }
+            "--bars" => {
+                bars = true;
+                i += 1;
+            }
+            "--pattern-scan" => {
+                pattern_scan = true;
+                i += 1;
+            }
+            "--min-run" => {
+                if i + 1 < args.len() {
+                    min_run = match args[i + 1].parse() {
+                        Ok(n) if n > 0 => Some(n),
+                        _ => {
+                            eprintln!("error: --min-run expects a positive integer");
+                            process::exit(2);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+            }
+            "--dump-to" => {
+                if i + 1 < args.len() {
+                    dump_to = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --dump-to requires a path");
+                    process::exit(2);
+                }
+            }
+            "--offset-width" => {
+                if i + 1 < args.len() {
+                    offset_width = match args[i + 1].parse() {
+                        Ok(n) if (1..=16).contains(&n) => n,
+                        _ => {
+                            eprintln!("error: --offset-width expects a number from 1 to 16");
+                            process::exit(2);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+            }
+            "--canonical" => {
+                canonical = true;
+                i += 1;
+            }
+            "--annotate" => {
+                annotate = true;
+                i += 1;
+            }
+            "--stdin" => {
+                use_stdin = true;
+                i += 1;
+            }
+            "--after" => {
+                if i + 1 < args.len() {
+                    after_sig = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --after requires a hex signature");
+                    process::exit(2);
+                }
+            }
+            "--interactive" => {
+                interactive = true;
+                i += 1;
+            }
+            "--dry-run" => {
+                dry_run = true;
+                i += 1;
+            }
             _ => {
                 eprintln!("error: Invalid option {}", args[i]);
                 process::exit(2);
@@ -99,6 +623,155 @@ fn main() {
         }
     }
 
+    if let Some((a, b)) = crc_files {
+        let crc_a = crc32_file(&a).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        });
+        let crc_b = crc32_file(&b).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        });
+
+        println!("{:08x}  {}", crc_a, a);
+        println!("{:08x}  {}", crc_b, b);
+        if crc_a == crc_b {
+            println!("✓ CRC-32 match");
+        } else {
+            println!("✗ CRC-32 mismatch");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some((a, b)) = diff_files {
+        let window_a = read_range_or_exit(&a, offset, size);
+        let window_b = read_range_or_exit(&b, offset + align, size);
+        let len = window_a.len().max(window_b.len());
+        if len == 0 {
+            eprintln!("error: No bytes to compare at offset 0x{:08x}", offset);
+            process::exit(1);
+        }
+
+        let mut differences = 0;
+        for i in 0..len {
+            let byte_a = window_a.get(i);
+            let byte_b = window_b.get(i);
+            if byte_a == byte_b {
+                continue;
+            }
+            differences += 1;
+            if differences <= DIFF_DISPLAY_LIMIT {
+                let show = |b: Option<&u8>| match b {
+                    Some(v) => format!("{:02x}", v),
+                    None => "--".to_string(),
+                };
+                println!(
+                    "{:08x}: {} {}",
+                    offset + i as u64,
+                    show(byte_a),
+                    show(byte_b)
+                );
+            }
+        }
+
+        if differences > DIFF_DISPLAY_LIMIT {
+            println!("... {} more", differences - DIFF_DISPLAY_LIMIT);
+        }
+        if align > 0 {
+            println!(
+                "{} of {} bytes matched with {} shifted by {} bytes",
+                len - differences,
+                len,
+                b,
+                align
+            );
+        }
+        if differences == 0 {
+            println!("✓ {} bytes identical at offset 0x{:08x}", len, offset);
+        } else {
+            println!("✗ {} byte(s) differ", differences);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some((a, b)) = visual_diff_files {
+        let window_a = read_range_or_exit(&a, offset, size);
+        let window_b = read_range_or_exit(&b, offset + align, size);
+        let len = window_a.len().max(window_b.len());
+        if len == 0 {
+            eprintln!("error: No bytes to compare at offset 0x{:08x}", offset);
+            process::exit(1);
+        }
+
+        let mut differences = 0;
+        for row in (0..len).step_by(VISUAL_DIFF_WIDTH) {
+            let end = (row + VISUAL_DIFF_WIDTH).min(len);
+            let mut left = String::new();
+            let mut right = String::new();
+            for i in row..end {
+                let byte_a = window_a.get(i);
+                let byte_b = window_b.get(i);
+                let differs = byte_a != byte_b;
+                if differs {
+                    differences += 1;
+                }
+                left.push_str(&format_diff_cell(byte_a, differs, !no_color));
+                right.push_str(&format_diff_cell(byte_b, differs, !no_color));
+            }
+            for _ in end..row + VISUAL_DIFF_WIDTH {
+                left.push_str("    ");
+            }
+            println!(
+                "{:08x}: {} | {}",
+                offset + row as u64,
+                left,
+                right.trim_end()
+            );
+        }
+
+        if differences == 0 {
+            println!("✓ {} bytes identical at offset 0x{:08x}", len, offset);
+        } else {
+            println!("✗ {} byte(s) differ", differences);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if use_stdin {
+        if !read_mode {
+            eprintln!("error: --stdin is only supported with --read");
+            process::exit(2);
+        }
+        if offset_given {
+            eprintln!("error: --offset cannot be used with --stdin");
+            process::exit(2);
+        }
+
+        let mut buffer = Vec::new();
+        let result = match size {
+            Some(s) => io::stdin().take(s as u64).read_to_end(&mut buffer),
+            None => io::stdin().read_to_end(&mut buffer),
+        };
+        if let Err(e) = result {
+            eprintln!("error: Failed to read stdin: {}", e);
+            process::exit(1);
+        }
+
+        if canonical {
+            print_canonical_dump(&mut io::stdout(), &buffer, 0).expect("Failed to write dump");
+        } else {
+            print_hex_dump(&mut io::stdout(), &buffer, 0, offset_width)
+                .expect("Failed to write dump");
+        }
+        if annotate {
+            println!("Format: {}", detect_format(&buffer));
+        }
+        return;
+    }
+
     let path = match file_path {
         Some(p) => p,
         None => {
@@ -107,8 +780,101 @@ fn main() {
         }
     };
 
-    if let Some(hex_str) = write_data {
-        let bytes_to_write = match hex_string_to_bytes(&hex_str) {
+    if let Some(list_path) = verify_list {
+        let list = std::fs::read_to_string(&list_path).unwrap_or_else(|e| {
+            eprintln!("error: Failed to read {}: {}", list_path, e);
+            process::exit(1);
+        });
+        let file_len = std::fs::metadata(&path)
+            .unwrap_or_else(|e| {
+                eprintln!("error: Failed to open {}: {}", path, e);
+                process::exit(1);
+            })
+            .len();
+
+        let mut passed = 0;
+        let mut failed = 0;
+        for (n, line) in list.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (region_offset, region_size, expected) = match parse_verify_line(line) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("error: {} line {}: {}", list_path, n + 1, e);
+                    process::exit(2);
+                }
+            };
+
+            let label = format!("0x{:08x} +{}", region_offset, region_size);
+            let data = read_range_or_exit(&path, region_offset, Some(region_size));
+            if data.len() < region_size {
+                println!(
+                    "✗ {}  region ends past the end of the file ({} bytes)",
+                    label, file_len
+                );
+                failed += 1;
+                continue;
+            }
+            let actual = !crc32_update(0xFFFFFFFF, &data);
+            if actual == expected {
+                println!("✓ {}  {:08x}", label, actual);
+                passed += 1;
+            } else {
+                println!("✗ {}  expected {:08x}, got {:08x}", label, expected, actual);
+                failed += 1;
+            }
+        }
+
+        println!("{} passed, {} failed", passed, failed);
+        if failed > 0 {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(new_len) = truncate_len {
+        if read_mode || write_data.is_some() || repeat_pattern.is_some() || write_text.is_some() {
+            eprintln!("error: --truncate cannot be combined with --read or --write");
+            process::exit(2);
+        }
+
+        let file = OpenOptions::new()
+            .write(!dry_run)
+            .read(true)
+            .open(&path)
+            .expect("Failed to open file");
+        let old_len = file.metadata().unwrap().len();
+
+        println!("Old size: {} bytes", old_len);
+        println!("New size: {} bytes", new_len);
+        if dry_run {
+            println!("(dry run, nothing written)");
+        } else {
+            if interactive {
+                confirm(&format!("truncate {} to {} bytes?", path, new_len));
+            }
+            file.set_len(new_len).expect("Failed to set file length");
+            println!("✓ Successfully truncated");
+        }
+        return;
+    }
+
+    let write_modes = [
+        write_data.is_some(),
+        repeat_pattern.is_some(),
+        write_text.is_some(),
+    ];
+    if write_modes.iter().filter(|&&m| m).count() > 1 {
+        eprintln!("error: --write, --repeat-write and --write-text cannot be combined");
+        process::exit(2);
+    }
+
+    let bytes_to_write = if let Some(text) = &write_text {
+        Some(text.as_bytes().to_vec())
+    } else if let Some(hex_str) = write_data.as_ref().or(repeat_pattern.as_ref()) {
+        let mut bytes = match hex_string_to_bytes(hex_str) {
             Ok(b) => b,
             Err(e) => {
                 eprintln!("Error parsing hex string: {}", e);
@@ -116,6 +882,81 @@ fn main() {
             }
         };
 
+        if repeat_pattern.is_some() {
+            if bytes.is_empty() {
+                eprintln!("error: --repeat-write pattern must not be empty");
+                process::exit(2);
+            }
+            let fill_len = size.unwrap_or_else(|| {
+                eprintln!("error: --repeat-write requires --size");
+                process::exit(2);
+            });
+            bytes = bytes.iter().cycle().take(fill_len).cloned().collect();
+        }
+        Some(bytes)
+    } else {
+        None
+    };
+
+    if let Some(bytes_to_write) = bytes_to_write {
+        let offset = match &after_sig {
+            Some(sig_str) => {
+                let signature = match hex_string_to_bytes(sig_str) {
+                    Ok(b) if !b.is_empty() => b,
+                    Ok(_) => {
+                        eprintln!("error: --after signature must not be empty");
+                        process::exit(2);
+                    }
+                    Err(e) => {
+                        eprintln!("Error parsing hex signature: {}", e);
+                        process::exit(1);
+                    }
+                };
+                let data = read_range_or_exit(&path, offset, None);
+                match find_pattern(&data, &signature) {
+                    Some(pos) => {
+                        let found = offset + pos as u64;
+                        println!("Signature found at offset 0x{:08x}", found);
+                        found + signature.len() as u64
+                    }
+                    None => {
+                        eprintln!("error: Signature {} not found", sig_str);
+                        process::exit(1);
+                    }
+                }
+            }
+            None => offset,
+        };
+
+        if dry_run {
+            let original = if std::path::Path::new(&path).exists() {
+                read_range_or_exit(&path, offset, Some(bytes_to_write.len()))
+            } else {
+                Vec::new()
+            };
+            println!(
+                "Writing {} bytes at offset 0x{:08x}",
+                bytes_to_write.len(),
+                offset
+            );
+            print!("Before:");
+            for b in &original {
+                print!(" {:02x}", b);
+            }
+            println!();
+            print!("After: ");
+            for b in &bytes_to_write {
+                print!(" {:02x}", b);
+            }
+            println!();
+            println!("(dry run, nothing written)");
+            return;
+        }
+
+        if interactive && std::path::Path::new(&path).exists() {
+            confirm(&format!("overwrite region at offset 0x{:08x}?", offset));
+        }
+
         let mut file = OpenOptions::new()
             .write(true)
             .create(true)
@@ -151,49 +992,319 @@ fn main() {
         return;
     }
 
-    if read_mode {
-        let mut file = File::open(&path).expect("File not found");
+    if let Some(bit) = flip_bit {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(!dry_run)
+            .open(&path)
+            .expect("Failed to open file");
+
+        if offset >= file.metadata().unwrap().len() {
+            eprintln!("error: Offset is beyond end of file");
+            process::exit(1);
+        }
+
+        let mut byte = [0u8; 1];
+        file.seek(SeekFrom::Start(offset)).expect("Failed to seek");
+        file.read_exact(&mut byte).expect("Failed to read byte");
+        let flipped = byte[0] ^ (1 << bit);
+
+        println!("Flipping bit {} at offset 0x{:08x}", bit, offset);
+        println!("Before: {:08b} (0x{:02x})", byte[0], byte[0]);
+        println!("After:  {:08b} (0x{:02x})", flipped, flipped);
+
+        if dry_run {
+            println!("(dry run, nothing written)");
+        } else {
+            if interactive {
+                confirm(&format!("flip bit {} at offset 0x{:08x}?", bit, offset));
+            }
+            file.seek(SeekFrom::Start(offset)).expect("Failed to seek");
+            file.write_all(&[flipped]).expect("Failed to write byte");
+            println!("✓ Successfully flipped");
+        }
+        return;
+    }
+
+    if let Some(width) = swap_width {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(!dry_run)
+            .open(&path)
+            .expect("Failed to open file");
 
         let file_len = file.metadata().unwrap().len();
         if offset > file_len {
-            return;
+            eprintln!("error: Offset is beyond end of file");
+            process::exit(1);
         }
 
+        let bytes_to_swap = match size {
+            Some(s) => s.min((file_len - offset) as usize),
+            None => (file_len - offset) as usize,
+        };
+
         file.seek(SeekFrom::Start(offset)).expect("Failed to seek");
+        let mut buffer = vec![0; bytes_to_swap];
+        file.read_exact(&mut buffer).expect("Failed to read file");
+
+        let original = buffer.clone();
+        let mut chunks = buffer.chunks_exact_mut(width);
+        for group in &mut chunks {
+            group.reverse();
+        }
+        let remainder = chunks.into_remainder().len();
+        if remainder > 0 {
+            eprintln!(
+                "warning: Trailing {} byte(s) do not fill a {}-byte group and were left unchanged",
+                remainder, width
+            );
+        }
+
+        println!(
+            "Swapping {} bytes in {}-byte groups at offset 0x{:08x}",
+            bytes_to_swap, width, offset
+        );
+        print!("Before:");
+        for b in &original {
+            print!(" {:02x}", b);
+        }
+        println!();
+        print!("After: ");
+        for b in &buffer {
+            print!(" {:02x}", b);
+        }
+        println!();
 
-        let bytes_to_read = match size {
-            Some(s) => s,
+        if dry_run {
+            println!("(dry run, nothing written)");
+        } else {
+            if interactive {
+                confirm(&format!(
+                    "swap {} bytes at offset 0x{:08x}?",
+                    bytes_to_swap, offset
+                ));
+            }
+            file.seek(SeekFrom::Start(offset)).expect("Failed to seek");
+            file.write_all(&buffer).expect("Failed to write bytes");
+            println!("✓ Successfully swapped");
+        }
+        return;
+    }
+
+    if let Some(key_str) = xor_key {
+        let key = match hex_string_to_bytes(&key_str) {
+            Ok(k) if !k.is_empty() => k,
+            Ok(_) => {
+                eprintln!("error: --xor key must not be empty");
+                process::exit(2);
+            }
+            Err(e) => {
+                eprintln!("Error parsing hex key: {}", e);
+                process::exit(1);
+            }
+        };
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(!dry_run)
+            .open(&path)
+            .expect("Failed to open file");
+
+        let file_len = file.metadata().unwrap().len();
+        if offset > file_len {
+            eprintln!("error: Offset is beyond end of file");
+            process::exit(1);
+        }
+
+        let bytes_to_xor = match size {
+            Some(s) => s.min((file_len - offset) as usize),
             None => (file_len - offset) as usize,
         };
 
-        let mut buffer = vec![0; bytes_to_read];
-        let bytes_read = file.read(&mut buffer).expect("Failed to read file");
+        file.seek(SeekFrom::Start(offset)).expect("Failed to seek");
+        let mut buffer = vec![0; bytes_to_xor];
+        file.read_exact(&mut buffer).expect("Failed to read file");
 
-        for (line_idx, chunk) in buffer[..bytes_read].chunks(16).enumerate() {
-            let current_offset = offset + (line_idx * 16) as u64;
+        for (b, k) in buffer.iter_mut().zip(key.iter().cycle()) {
+            *b ^= k;
+        }
 
-            print!("{:08x}:", current_offset);
+        println!(
+            "XOR {} bytes with {}-byte key at offset 0x{:08x}",
+            bytes_to_xor,
+            key.len(),
+            offset
+        );
+        print_hex_dump(&mut io::stdout(), &buffer, offset, offset_width)
+            .expect("Failed to write dump");
 
-            for byte in chunk {
-                print!(" {:02x}", byte);
+        if dry_run {
+            println!("(dry run, nothing written)");
+        } else {
+            if interactive {
+                confirm(&format!(
+                    "XOR {} bytes at offset 0x{:08x}?",
+                    bytes_to_xor, offset
+                ));
             }
+            file.seek(SeekFrom::Start(offset)).expect("Failed to seek");
+            file.write_all(&buffer).expect("Failed to write bytes");
+            println!("✓ Successfully XORed");
+        }
+        return;
+    }
 
-            if chunk.len() < 16 {
-                for _ in 0..(16 - chunk.len()) {
-                    print!("   ");
-                }
+    if strings_mode {
+        let buffer = read_range_or_exit(&path, offset, size);
+        let mut lengths: Vec<usize> = Vec::new();
+        for (start, text) in find_strings(&buffer, min_run.unwrap_or(4)) {
+            println!("{:08x}: {}", offset + start as u64, text);
+            lengths.push(text.len());
+        }
+        if strings_stats {
+            match (lengths.iter().min(), lengths.iter().max()) {
+                (Some(min), Some(max)) => println!(
+                    "{} strings, length min {} / max {} / avg {:.1}",
+                    lengths.len(),
+                    min,
+                    max,
+                    lengths.iter().sum::<usize>() as f64 / lengths.len() as f64
+                ),
+                _ => println!("0 strings"),
             }
+        }
+        return;
+    }
 
-            print!(" |");
+    if histogram {
+        let buffer = read_range_or_exit(&path, offset, size);
+        if buffer.is_empty() {
+            println!("no bytes in range");
+            process::exit(1);
+        }
+        let counts = byte_histogram(&buffer);
+        let mut rows: Vec<(u8, u64)> = (0..=255u8)
+            .map(|b| (b, counts[b as usize]))
+            .filter(|&(_, n)| n > 0)
+            .collect();
+        if by_count {
+            rows.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        }
 
-            for byte in chunk {
-                if *byte >= 0x20 && *byte <= 0x7E {
-                    print!("{}", *byte as char);
-                } else {
-                    print!(".");
-                }
+        let total = buffer.len() as f64;
+        let peak = rows.iter().map(|&(_, n)| n).max().unwrap_or(1);
+        for &(byte, n) in &rows {
+            let line = format!(
+                "0x{:02x}  {:>10}  {:>6.2}%",
+                byte,
+                n,
+                n as f64 * 100.0 / total
+            );
+            if bars {
+                let width = (n * HISTOGRAM_BAR_WIDTH).div_ceil(peak) as usize;
+                println!("{}  {}", line, "#".repeat(width));
+            } else {
+                println!("{}", line);
+            }
+        }
+
+        let most = rows.iter().max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+        let least = rows.iter().min_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+        if let (Some(&(most, most_n)), Some(&(least, least_n))) = (most, least) {
+            println!(
+                "{} of 256 values present; most common 0x{:02x} ({}), least common 0x{:02x} ({})",
+                rows.len(),
+                most,
+                most_n,
+                least,
+                least_n
+            );
+        }
+        return;
+    }
+
+    if pattern_scan {
+        let min_run = min_run.unwrap_or(16);
+        let buffer = read_range_or_exit(&path, offset, size);
+        let runs = find_runs(&buffer, min_run);
+        if runs.is_empty() {
+            println!("no runs ≥ {} found", min_run);
+            process::exit(1);
+        }
+        for (start, byte, len) in runs {
+            println!(
+                "{:08x}: byte 0x{:02x} repeated {} times",
+                offset + start as u64,
+                byte,
+                len
+            );
+        }
+        return;
+    }
+
+    if read_mode {
+        let buffer = match read_range(&path, offset, size) {
+            Ok(Some(b)) => b,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("error: Failed to read {}: {}", path, e);
+                process::exit(1);
             }
-            println!("|");
+        };
+
+        let mut out: Box<dyn Write> = match &dump_to {
+            Some(dump_path) => Box::new(BufWriter::new(
+                File::create(dump_path).expect("Failed to create dump file"),
+            )),
+            None => Box::new(io::stdout().lock()),
+        };
+        if canonical {
+            print_canonical_dump(&mut out, &buffer, offset)
+        } else {
+            print_hex_dump(&mut out, &buffer, offset, offset_width)
         }
+        .and_then(|_| out.flush())
+        .expect("Failed to write dump");
+        drop(out);
+
+        if let Some(dump_path) = &dump_to {
+            eprintln!("dumped {} bytes to {}", buffer.len(), dump_path);
+        }
+        if annotate {
+            println!("Format: {}", detect_format(&buffer));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_dump_matches_hexdump_c() {
+        let mut data = b"0123456789abcdef".to_vec();
+        data.extend([0u8; 32]);
+        data.extend(b"xyz");
+
+        let mut out = Vec::new();
+        print_canonical_dump(&mut out, &data, 0).unwrap();
+
+        // What `hexdump -C` prints for the same bytes.
+        let expected = "\
+00000000  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|
+00000010  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|
+*
+00000030  78 79 7a                                          |xyz|
+00000033
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn canonical_dump_of_nothing_is_empty() {
+        let mut out = Vec::new();
+        print_canonical_dump(&mut out, &[], 0x10).unwrap();
+        assert!(out.is_empty());
     }
 }
@@ -1,14 +1,56 @@
 use rand::Rng;
 use std::env;
-use std::io::{self, BufRead, Read, Write};
-use std::net::{TcpListener, TcpStream};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const P: u64 = 0xD87FA3E291B4C7F3;
 const G: u64 = 2;
 
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+macro_rules! info_inline {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            print!($($arg)*);
+        }
+    };
+}
+
+// Exit codes, documented in --help.
+const EXIT_CONNECTION: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_HANDSHAKE: i32 = 3;
+const EXIT_IO: i32 = 4;
+
+fn fail(code: i32, context: &str, e: io::Error) -> ! {
+    eprintln!("error: {}: {}", context, e);
+    process::exit(code);
+}
+
+const FRAME_CHAT: u8 = 0x01;
+const FRAME_KEEPALIVE: u8 = 0x02;
+const FRAME_WHO: u8 = 0x03;
+const FRAME_WHO_REPLY: u8 = 0x04;
+
+// Sent by a relay to each peer once both are connected, so exactly one side
+// takes the server's role in the handshake.
+const RELAY_LEAD: u8 = 0x00;
+const RELAY_FOLLOW: u8 = 0x01;
+
 struct Lcg {
     state: u64,
     position: usize,
@@ -45,12 +87,355 @@ fn mod_pow(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
     result
 }
 
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// Frame header: kind (1 byte), sequence number (4 bytes), payload length
+// (2 bytes), all big-endian. Every frame kind shares one sequence counter.
+const FRAME_HEADER_LEN: usize = 7;
+
+// Raw frames in hex, one per line: unix time, direction, then the bytes.
+// Chat payloads are logged as sent on the wire, i.e. ciphertext.
+type HexLog = Arc<Mutex<BufWriter<File>>>;
+
+fn log_frame(log: &HexLog, direction: &str, bytes: &[u8]) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let mut log = log.lock().unwrap();
+    let _ = writeln!(
+        log,
+        "{}.{:03} {} {}",
+        now.as_secs(),
+        now.subsec_millis(),
+        direction,
+        hex
+    )
+    .and_then(|_| log.flush());
+}
+
+struct FrameWriter {
+    stream: TcpStream,
+    next_seq: u32,
+    log: Option<HexLog>,
+}
+
+impl FrameWriter {
+    fn write_frame(&mut self, kind: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+        frame.push(kind);
+        frame.extend_from_slice(&self.next_seq.to_be_bytes());
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame)?;
+        if let Some(log) = &self.log {
+            log_frame(log, "send", &frame);
+        }
+        self.next_seq = self.next_seq.wrapping_add(1);
+        Ok(())
+    }
+}
+
+type Frame = (u8, u32, Result<Vec<u8>, usize>);
+
+// An oversized payload is drained from the socket and reported as Err(len)
+// so the stream stays aligned on the next frame header.
+// An oversized frame is logged as its header alone.
+fn read_frame(stream: &mut TcpStream, max_len: usize, log: Option<&HexLog>) -> io::Result<Frame> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    stream.read_exact(&mut header)?;
+    let seq = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+    let len = u16::from_be_bytes([header[5], header[6]]) as usize;
+    if len > max_len {
+        io::copy(&mut stream.take(len as u64), &mut io::sink())?;
+        if let Some(log) = log {
+            log_frame(log, "recv", &header);
+        }
+        return Ok((header[0], seq, Err(len)));
+    }
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload)?;
+    if let Some(log) = log {
+        log_frame(log, "recv", &[&header[..], &payload].concat());
+    }
+    Ok((header[0], seq, Ok(payload)))
+}
+
+fn check_sequence(expected: &mut u32, seq: u32) {
+    if seq != *expected {
+        let problem = if seq < *expected { "reorder" } else { "gap" };
+        eprintln!(
+            "\n[WARN] sequence {}: expected {} got {}",
+            problem, expected, seq
+        );
+    }
+    *expected = seq.wrapping_add(1);
+}
+
+fn apply_keystream(seed: u64, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut lcg = Lcg::new(seed);
+    let keystream: Vec<u8> = data.iter().map(|_| lcg.next_byte()).collect();
+    let output = data.iter().zip(&keystream).map(|(b, k)| b ^ k).collect();
+    (keystream, output)
+}
+
+fn parse_hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+    let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("hex input must have an even number of digits".to_string());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| format!("invalid hex byte '{}'", &digits[i..i + 2]))
+        })
+        .collect()
+}
+
+fn run_offline(decrypt: bool, args: &[String]) {
+    let command = if decrypt { "decrypt" } else { "encrypt" };
+    if args.len() < 2 {
+        eprintln!(
+            "Usage: cargo run -- {} <SEED_HEX> <MESSAGE | --file PATH>",
+            command
+        );
+        process::exit(EXIT_USAGE);
+    }
+
+    let seed_hex = args[0].trim_start_matches("0x");
+    let seed = u64::from_str_radix(seed_hex, 16).unwrap_or_else(|_| {
+        eprintln!(
+            "error: Invalid seed '{}', expected up to 16 hex digits",
+            args[0]
+        );
+        process::exit(EXIT_USAGE);
+    });
+
+    let input = if args[1] == "--file" {
+        let path = args.get(2).unwrap_or_else(|| {
+            eprintln!("error: --file requires a path");
+            process::exit(EXIT_USAGE);
+        });
+        std::fs::read(path)
+            .unwrap_or_else(|e| fail(EXIT_IO, &format!("Failed to read {}", path), e))
+    } else if decrypt {
+        parse_hex_bytes(&args[1..].join("")).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(EXIT_USAGE);
+        })
+    } else {
+        args[1..].join(" ").into_bytes()
+    };
+
+    let (keystream, output) = apply_keystream(seed, &input);
+
+    println!("[STREAM] Seed: {:016X}", seed);
+    print!("  Input: ");
+    for b in &input {
+        print!(" {:02x}", b);
+    }
+    println!();
+    print!("  Key:   ");
+    for k in &keystream {
+        print!(" {:02x}", k);
+    }
+    println!();
+    print!("  Output:");
+    for b in &output {
+        print!(" {:02x}", b);
+    }
+    println!();
+    println!("  Text:   \"{}\"", String::from_utf8_lossy(&output));
+}
+
+fn load_or_create_key(path: &str) -> u64 {
+    eprintln!("warning: --key-file persists the DH private key, use for development only");
+    match std::fs::read_to_string(path) {
+        Ok(text) => u64::from_str_radix(text.trim(), 16).unwrap_or_else(|_| {
+            eprintln!("error: {} does not contain a hex private key", path);
+            process::exit(EXIT_HANDSHAKE);
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let key: u64 = rand::thread_rng().gen();
+            if let Err(e) = std::fs::write(path, format!("{:016X}\n", key)) {
+                fail(EXIT_HANDSHAKE, &format!("Failed to write {}", path), e);
+            }
+            key
+        }
+        Err(e) => fail(EXIT_HANDSHAKE, &format!("Failed to read {}", path), e),
+    }
+}
+
+struct ChatOptions {
+    password: Option<String>,
+    keepalive: Option<u64>,
+    ipv6: bool,
+    max_message_size: usize,
+    quiet: bool,
+    key_file: Option<String>,
+    psk: Option<u64>,
+    log_hex: Option<String>,
+    relayed: bool,
+}
+
+fn parse_options(args: &[String]) -> ChatOptions {
+    let mut opts = ChatOptions {
+        password: None,
+        keepalive: None,
+        ipv6: false,
+        max_message_size: 4096,
+        quiet: false,
+        key_file: None,
+        psk: None,
+        log_hex: None,
+        relayed: false,
+    };
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--password" => {
+                if i + 1 < args.len() {
+                    opts.password = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --password requires a value");
+                    process::exit(EXIT_USAGE);
+                }
+            }
+            "--keepalive" => {
+                if i + 1 < args.len() {
+                    opts.keepalive = match args[i + 1].parse::<u64>() {
+                        Ok(n) if n > 0 => Some(n),
+                        _ => {
+                            eprintln!("error: --keepalive expects a positive number of seconds");
+                            process::exit(EXIT_USAGE);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    eprintln!("error: --keepalive requires a value");
+                    process::exit(EXIT_USAGE);
+                }
+            }
+            "--max-message-size" => {
+                if i + 1 < args.len() {
+                    opts.max_message_size = match args[i + 1].parse::<usize>() {
+                        Ok(n) if n > 0 && n <= u16::MAX as usize => n,
+                        _ => {
+                            eprintln!(
+                                "error: --max-message-size expects a number from 1 to {}",
+                                u16::MAX
+                            );
+                            process::exit(EXIT_USAGE);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    eprintln!("error: --max-message-size requires a value");
+                    process::exit(EXIT_USAGE);
+                }
+            }
+            "--key-file" => {
+                if i + 1 < args.len() {
+                    opts.key_file = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --key-file requires a path");
+                    process::exit(EXIT_USAGE);
+                }
+            }
+            "--psk" => {
+                if i + 1 < args.len() {
+                    let hex = args[i + 1].trim_start_matches("0x");
+                    opts.psk = Some(u64::from_str_radix(hex, 16).unwrap_or_else(|_| {
+                        eprintln!("error: --psk expects up to 16 hex digits");
+                        process::exit(EXIT_USAGE);
+                    }));
+                    i += 2;
+                } else {
+                    eprintln!("error: --psk requires a hex key");
+                    process::exit(EXIT_USAGE);
+                }
+            }
+            "--log-hex" => {
+                if i + 1 < args.len() {
+                    opts.log_hex = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("error: --log-hex requires a path");
+                    process::exit(EXIT_USAGE);
+                }
+            }
+            "--relayed" => {
+                opts.relayed = true;
+                i += 1;
+            }
+            "--quiet" => {
+                opts.quiet = true;
+                i += 1;
+            }
+            "--ipv6" => {
+                opts.ipv6 = true;
+                i += 1;
+            }
+            other => {
+                eprintln!("error: Invalid option '{}'", other);
+                process::exit(EXIT_USAGE);
+            }
+        }
+    }
+    if opts.psk.is_some() && (opts.password.is_some() || opts.key_file.is_some()) {
+        eprintln!(
+            "error: --psk skips key exchange and cannot be combined with --password or --key-file"
+        );
+        process::exit(EXIT_USAGE);
+    }
+    opts
+}
+
 fn print_help() {
     println!("Usage: streamchat");
     println!("\nStream cipher chat with Diffie-Hellman key generation");
     println!("\nCommands:");
     println!("  server Start server");
     println!("  client Connect to server");
+    println!("  relay <port>  Forward bytes between the next two clients without decrypting");
+    println!("                (both clients connect with --relayed)");
+    println!("  encrypt <seed> <message>  XOR a message with the keystream offline");
+    println!("  decrypt <seed> <hex>      XOR hex ciphertext with the keystream offline");
+    println!("                            (use --file <path> instead of a message for raw bytes)");
+    println!("\nChat commands:");
+    println!("  /who                 Show the socket endpoints of this connection");
+    println!("\nOptions:");
+    println!("  --password <phrase>  Pre-shared password mixed into the keystream seed");
+    println!("                       (adds an authentication factor on top of DH)");
+    println!("  --keepalive <secs>   Ping the peer when idle and drop it after 3x secs of silence");
+    println!("                       (enable on both sides)");
+    println!("  --max-message-size <n>  Reject messages longer than n bytes [default: 4096]");
+    println!("  --key-file <path>    Load the DH private key from path, or generate and save it");
+    println!("                       (development only: a persisted private key is insecure)");
+    println!("  --psk <hex>          Seed the keystream from a pre-shared key and skip DH");
+    println!("                       (both peers must pass the same key or messages garble)");
+    println!("  --log-hex <path>     Append every raw frame sent or received to path as hex");
+    println!("                       (chat frames are logged as ciphertext)");
+    println!("  --relayed            Client: the address is a relay, not a server");
+    println!("  --quiet              Only show chat messages; warnings and errors go to stderr");
+    println!("  --ipv6               Server: listen on [::] instead of 0.0.0.0");
+    println!("                       (clients connect with a bracketed address like [::1]:9000)");
+    println!("\nExit codes:");
+    println!("  0  Chat ended normally (either side closed, or end of input)");
+    println!("  1  Connection error (bind, connect, or peer went silent)");
+    println!("  2  Usage error");
+    println!("  3  Handshake failure (key exchange, password or key file)");
+    println!("  4  I/O error during chat or while reading input files");
 }
 
 fn main() {
@@ -66,183 +451,438 @@ fn main() {
         }
         "server" => {
             if args.len() < 3 {
-                eprintln!("Usage: cargo run -- server <PORT>");
-                process::exit(1);
+                eprintln!("Usage: cargo run -- server <PORT> [--password <phrase>]");
+                process::exit(EXIT_USAGE);
             }
-            run_server(&args[2]);
+            let opts = parse_options(&args[3..]);
+            QUIET.store(opts.quiet, Ordering::Relaxed);
+            run_server(&args[2], &opts);
         }
         "client" => {
             if args.len() < 3 {
-                eprintln!("Usage: cargo run -- client <ADDRESS>");
-                process::exit(1);
+                eprintln!("Usage: cargo run -- client <ADDRESS> [--password <phrase>]");
+                process::exit(EXIT_USAGE);
+            }
+            let opts = parse_options(&args[3..]);
+            QUIET.store(opts.quiet, Ordering::Relaxed);
+            run_client(&args[2], &opts);
+        }
+        "relay" => {
+            if args.len() < 3 {
+                eprintln!("Usage: cargo run -- relay <PORT>");
+                process::exit(EXIT_USAGE);
             }
-            run_client(&args[2]);
+            let opts = parse_options(&args[3..]);
+            QUIET.store(opts.quiet, Ordering::Relaxed);
+            run_relay(&args[2], &opts);
         }
+        "encrypt" => run_offline(false, &args[2..]),
+        "decrypt" => run_offline(true, &args[2..]),
         _ => {
             eprintln!("error: Invalid command '{}'", args[1]);
             print_help();
-            process::exit(2);
+            process::exit(EXIT_USAGE);
         }
     }
 }
 
-fn run_server(port: &str) {
-    let address = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&address).expect("Failed to bind");
-    println!("[SERVER] Listening on {}", address);
-    println!("[SERVER] DH parameters:");
-    println!("  p = {:016X}", P);
-    println!("  g = {}", G);
-    println!("[SERVER] Waiting for client...");
-
-    if let Ok((stream, addr)) = listener.accept() {
-        println!("\n[CLIENT] Connected from {}", addr);
-        handle_connection(stream, true);
+fn run_server(port: &str, opts: &ChatOptions) {
+    let address = if opts.ipv6 {
+        format!("[::]:{}", port)
+    } else {
+        format!("0.0.0.0:{}", port)
+    };
+    let listener = TcpListener::bind(&address)
+        .unwrap_or_else(|e| fail(EXIT_CONNECTION, &format!("Failed to bind {}", address), e));
+    info!("[SERVER] Listening on {}", address);
+    if opts.psk.is_none() {
+        info!("[SERVER] DH parameters:");
+        info!("  p = {:016X}", P);
+        info!("  g = {}", G);
+    }
+    info!("[SERVER] Waiting for client...");
+
+    match listener.accept() {
+        Ok((stream, addr)) => {
+            info!("\n[CLIENT] Connected from {}", addr);
+            handle_connection(stream, true, opts);
+        }
+        Err(e) => fail(EXIT_CONNECTION, "Failed to accept client", e),
     }
 }
 
-fn run_client(address: &str) {
-    println!("[CLIENT] Connecting to {}...", address);
+fn run_client(address: &str, opts: &ChatOptions) {
+    if address.matches(':').count() > 1 && !address.starts_with('[') {
+        eprintln!("error: IPv6 addresses must be bracketed with the port outside, e.g. [::1]:9000");
+        process::exit(EXIT_USAGE);
+    }
+
+    info!("[CLIENT] Connecting to {}...", address);
     match TcpStream::connect(address) {
-        Ok(stream) => {
-            println!("[CLIENT] Connected!");
-            handle_connection(stream, false);
-        }
-        Err(e) => {
-            eprintln!("Failed to connect: {}", e);
-            process::exit(1);
+        Ok(mut stream) => {
+            info!("[CLIENT] Connected!");
+            let mut leads = false;
+            if opts.relayed {
+                info!("[CLIENT] Waiting for the relay to pair us with a peer...");
+                let mut role = [0u8; 1];
+                stream
+                    .read_exact(&mut role)
+                    .unwrap_or_else(|e| fail(EXIT_CONNECTION, "Relay closed before pairing", e));
+                leads = role[0] == RELAY_LEAD;
+                info!("[CLIENT] Paired through relay");
+            }
+            handle_connection(stream, leads, opts);
         }
+        Err(e) => fail(EXIT_CONNECTION, "Failed to connect", e),
     }
 }
 
-fn handle_connection(mut stream: TcpStream, is_server: bool) {
-    println!("\\n[DH] Starting key exchange...");
-    println!("[DH] Using hardcoded DH parameters:");
-    println!("  p = {:016X} (64-bit prime - public)", P);
-    println!("  g = {} (generator - public)", G);
+// The relay never sees keys: the two peers run the handshake through it as
+// if they were directly connected, and it copies bytes until either side
+// hangs up.
+fn run_relay(port: &str, opts: &ChatOptions) {
+    let address = if opts.ipv6 {
+        format!("[::]:{}", port)
+    } else {
+        format!("0.0.0.0:{}", port)
+    };
+    let listener = TcpListener::bind(&address)
+        .unwrap_or_else(|e| fail(EXIT_CONNECTION, &format!("Failed to bind {}", address), e));
+    info!("[RELAY] Listening on {}", address);
+
+    let accept = |label: &str| {
+        info!("[RELAY] Waiting for {} client...", label);
+        let (stream, addr) = listener
+            .accept()
+            .unwrap_or_else(|e| fail(EXIT_CONNECTION, "Failed to accept client", e));
+        info!("[RELAY] {} client connected from {}", label, addr);
+        stream
+    };
+    let mut first = accept("first");
+    let mut second = accept("second");
 
-    let private_key: u64 = rand::thread_rng().gen();
-    println!("\\n[DH] Generating our keypair...");
-    println!("  private_key = {:016X} (random 64-bit)", private_key);
+    if let Err(e) = first
+        .write_all(&[RELAY_LEAD])
+        .and_then(|_| second.write_all(&[RELAY_FOLLOW]))
+    {
+        fail(EXIT_CONNECTION, "Failed to pair clients", e);
+    }
+    info!("[RELAY] Forwarding...");
+
+    let forward = |mut from: TcpStream, mut to: TcpStream| {
+        thread::spawn(move || {
+            let copied = io::copy(&mut from, &mut to).unwrap_or(0);
+            let _ = from.shutdown(Shutdown::Both);
+            let _ = to.shutdown(Shutdown::Both);
+            copied
+        })
+    };
+    let clone = |stream: &TcpStream| {
+        stream
+            .try_clone()
+            .unwrap_or_else(|e| fail(EXIT_IO, "Failed to clone stream", e))
+    };
+    let up = forward(clone(&first), clone(&second));
+    let down = forward(second, first);
+    let sent_up = up.join().unwrap_or(0);
+    let sent_down = down.join().unwrap_or(0);
+    info!(
+        "[RELAY] Closed: {} bytes first → second, {} bytes second → first",
+        sent_up, sent_down
+    );
+}
+
+// Runs the DH exchange (plus the optional password mix) and confirms both
+// sides derived the same keystream seed.
+fn handshake_io<T>(result: io::Result<T>) -> T {
+    result.unwrap_or_else(|e| fail(EXIT_HANDSHAKE, "Key exchange failed", e))
+}
+
+fn negotiate_seed(stream: &mut TcpStream, is_server: bool, opts: &ChatOptions) -> u64 {
+    info!("\n[DH] Starting key exchange...");
+    info!("[DH] Using hardcoded DH parameters:");
+    info!("  p = {:016X} (64-bit prime - public)", P);
+    info!("  g = {} (generator - public)", G);
+
+    let private_key: u64 = match &opts.key_file {
+        Some(path) => load_or_create_key(path),
+        None => rand::thread_rng().gen(),
+    };
+    info!("\n[DH] Generating our keypair...");
+    let key_source = if opts.key_file.is_some() {
+        "from key file"
+    } else {
+        "random 64-bit"
+    };
+    info!("  private_key = {:016X} ({})", private_key, key_source);
 
     let public_key = mod_pow(G, private_key, P);
-    println!("  public_key  = g^private mod p");
-    println!("              = {}^{:016X} mod p", G, private_key);
-    println!("              = {:016X}", public_key);
+    info!("  public_key  = g^private mod p");
+    info!("              = {}^{:016X} mod p", G, private_key);
+    info!("              = {:016X}", public_key);
 
-    println!("\\n[DH] Exchanging keys...");
+    info!("\n[DH] Exchanging keys...");
 
     let peer_public_key = if is_server {
-        println!("[NETWORK] Sending public key (8 bytes)...");
-        stream.write_all(&public_key.to_be_bytes()).unwrap();
-        println!("  → Send our public:     {:016X}", public_key);
+        info!("[NETWORK] Sending public key (8 bytes)...");
+        handshake_io(stream.write_all(&public_key.to_be_bytes()));
+        info!("  → Send our public:     {:016X}", public_key);
 
         let mut buf = [0u8; 8];
-        stream.read_exact(&mut buf).unwrap();
+        handshake_io(stream.read_exact(&mut buf));
         let key = u64::from_be_bytes(buf);
-        println!("[NETWORK] Received public key (8 bytes) ✓");
-        println!("  ← Receive their public: {:016X}", key);
+        info!("[NETWORK] Received public key (8 bytes) ✓");
+        info!("  ← Receive their public: {:016X}", key);
         key
     } else {
         let mut buf = [0u8; 8];
-        stream.read_exact(&mut buf).unwrap();
+        handshake_io(stream.read_exact(&mut buf));
         let key = u64::from_be_bytes(buf);
-        println!("[NETWORK] Received public key (8 bytes) ✓");
-        println!("  ← Receive their public: {:016X}", key);
+        info!("[NETWORK] Received public key (8 bytes) ✓");
+        info!("  ← Receive their public: {:016X}", key);
 
-        println!("[NETWORK] Sending public key (8 bytes)...");
-        stream.write_all(&public_key.to_be_bytes()).unwrap();
-        println!("  → Send our public:     {:016X}", public_key);
+        info!("[NETWORK] Sending public key (8 bytes)...");
+        handshake_io(stream.write_all(&public_key.to_be_bytes()));
+        info!("  → Send our public:     {:016X}", public_key);
         key
     };
 
-    println!("\\n[DH] Computing shared secret...");
-    println!("  Formula: secret = (their_public)^(our_private) mod p");
+    info!("\n[DH] Computing shared secret...");
+    info!("  Formula: secret = (their_public)^(our_private) mod p");
     let shared_secret = mod_pow(peer_public_key, private_key, P);
-    println!(
-        "\\n  secret = ({:016X})^({:016X}) mod p",
+    info!(
+        "\n  secret = ({:016X})^({:016X}) mod p",
         peer_public_key, private_key
     );
-    println!("         = {:016X}", shared_secret);
+    info!("         = {:016X}", shared_secret);
+
+    let seed = match &opts.password {
+        Some(password) => {
+            let password_hash = fnv1a(password.as_bytes());
+            info!("\n[AUTH] Mixing password into secret...");
+            info!("  password_hash = {:016X} (FNV-1a)", password_hash);
+            info!(
+                "  seed = secret XOR password_hash = {:016X}",
+                shared_secret ^ password_hash
+            );
+            shared_secret ^ password_hash
+        }
+        None => shared_secret,
+    };
 
-    println!("\\n[VERIFY] Both sides computed the same secret ✓");
+    let check = fnv1a(&seed.to_be_bytes());
+    let peer_check = if is_server {
+        handshake_io(stream.write_all(&check.to_be_bytes()));
+        let mut buf = [0u8; 8];
+        handshake_io(stream.read_exact(&mut buf));
+        u64::from_be_bytes(buf)
+    } else {
+        let mut buf = [0u8; 8];
+        handshake_io(stream.read_exact(&mut buf));
+        handshake_io(stream.write_all(&check.to_be_bytes()));
+        u64::from_be_bytes(buf)
+    };
 
-    println!("\\n[STREAM] Generating keystream from secret...");
-    println!("  Algorithm: LCG (a=1103515245, c=12345, m=2^32)");
-    println!("  Seed: secret = {:016X}", shared_secret);
+    if peer_check != check {
+        if opts.password.is_some() {
+            eprintln!("error: password mismatch, keystreams would not match");
+        } else {
+            eprintln!("error: password mismatch (no --password given on this side)");
+        }
+        process::exit(EXIT_HANDSHAKE);
+    }
 
-    let lcg = Arc::new(Mutex::new(Lcg::new(shared_secret)));
+    info!("\n[VERIFY] Both sides computed the same secret ✓");
 
-    {
-        let mut temp_lcg = Lcg::new(shared_secret);
-        print!("\\n  Keystream:");
+    seed
+}
+
+fn handle_connection(mut stream: TcpStream, is_server: bool, opts: &ChatOptions) {
+    let seed = match opts.psk {
+        Some(psk) => psk,
+        None => negotiate_seed(&mut stream, is_server, opts),
+    };
+
+    let lcg = Arc::new(Mutex::new(Lcg::new(seed)));
+
+    if opts.psk.is_none() {
+        info!("\n[STREAM] Generating keystream from secret...");
+        info!("  Algorithm: LCG (a=1103515245, c=12345, m=2^32)");
+        info!("  Seed: {:016X}", seed);
+
+        let mut temp_lcg = Lcg::new(seed);
+        info_inline!("\n  Keystream:");
         for _ in 0..14 {
-            print!(" {:02X}", temp_lcg.next_byte());
+            info_inline!(" {:02X}", temp_lcg.next_byte());
         }
-        println!(" ...");
+        info!(" ...");
     }
 
-    println!("\\n✓ Secure channel established!");
-    println!("\\n[CHAT] Type message:");
+    info!("\n✓ Secure channel established!");
+    info!("\n[CHAT] Type message:");
 
-    let stream_clone = stream.try_clone().expect("Failed to clone stream");
+    let stream_clone = stream
+        .try_clone()
+        .unwrap_or_else(|e| fail(EXIT_IO, "Failed to clone stream", e));
     let lcg_clone = Arc::clone(&lcg);
+    let hex_log: Option<HexLog> = opts.log_hex.as_ref().map(|path| {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|e| fail(EXIT_IO, &format!("Failed to open {}", path), e));
+        Arc::new(Mutex::new(BufWriter::new(file)))
+    });
+    let reader_log = hex_log.clone();
+    let writer = Arc::new(Mutex::new(FrameWriter {
+        stream: stream
+            .try_clone()
+            .unwrap_or_else(|e| fail(EXIT_IO, "Failed to clone stream", e)),
+        next_seq: 0,
+        log: hex_log,
+    }));
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let last_received = Arc::new(Mutex::new(Instant::now()));
+    let last_sent = Arc::new(Mutex::new(Instant::now()));
+
+    if let Some(secs) = opts.keepalive {
+        let interval = Duration::from_secs(secs);
+        let writer = Arc::clone(&writer);
+        let shutdown = Arc::clone(&shutdown);
+        let last_received = Arc::clone(&last_received);
+        let last_sent = Arc::clone(&last_sent);
+        let stream = stream
+            .try_clone()
+            .unwrap_or_else(|e| fail(EXIT_IO, "Failed to clone stream", e));
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            if last_received.lock().unwrap().elapsed() > interval * 3 {
+                eprintln!("\n[KEEPALIVE] peer unresponsive, closing connection");
+                shutdown.store(true, Ordering::SeqCst);
+                let _ = stream.shutdown(Shutdown::Both);
+                return;
+            }
+            if last_sent.lock().unwrap().elapsed() >= interval {
+                if writer
+                    .lock()
+                    .unwrap()
+                    .write_frame(FRAME_KEEPALIVE, &[])
+                    .is_err()
+                {
+                    return;
+                }
+                *last_sent.lock().unwrap() = Instant::now();
+            }
+        });
+    }
 
-    thread::spawn(move || {
-        let mut buffer = [0u8; 1024];
+    // Only the side that led the handshake answers /who; the list is sent in
+    // the clear so it never advances the keystream. These are socket
+    // addresses, so through a relay the far end is the relay itself.
+    let who_list = match (stream.local_addr(), stream.peer_addr()) {
+        (Ok(local), Ok(peer)) if opts.relayed => {
+            format!(
+                "endpoints: {} ↔ relay {} (peer hidden by relay)",
+                local, peer
+            )
+        }
+        (Ok(local), Ok(peer)) => format!("endpoints: server {} ↔ client {}", local, peer),
+        _ => "unknown".to_string(),
+    };
+    let who_reply = who_list.clone();
+    let who_writer = Arc::clone(&writer);
+
+    let max_len = opts.max_message_size;
+    let reader_shutdown = Arc::clone(&shutdown);
+    let reader = thread::spawn(move || {
         let mut stream = stream_clone;
+        let mut expected_seq = 0;
         loop {
-            match stream.read(&mut buffer) {
-                Ok(n) if n > 0 => {
-                    println!("\\n[NETWORK] Received encrypted message ({} bytes)", n);
-                    println!("[←] Received {} bytes", n);
+            match read_frame(&mut stream, max_len, reader_log.as_ref()) {
+                Ok((kind, seq, payload)) => {
+                    *last_received.lock().unwrap() = Instant::now();
+                    check_sequence(&mut expected_seq, seq);
+                    if kind == FRAME_WHO && is_server {
+                        let _ = who_writer
+                            .lock()
+                            .unwrap()
+                            .write_frame(FRAME_WHO_REPLY, who_reply.as_bytes());
+                        continue;
+                    }
+                    if kind == FRAME_WHO_REPLY && !is_server {
+                        if let Ok(list) = payload {
+                            println!("\n[WHO] {}", String::from_utf8_lossy(&list));
+                        }
+                        continue;
+                    }
+                    if kind != FRAME_CHAT {
+                        continue;
+                    }
+                    let buffer = match payload {
+                        Ok(b) => b,
+                        Err(len) => {
+                            let mut lcg = lcg_clone.lock().unwrap();
+                            for _ in 0..len {
+                                lcg.next_byte();
+                            }
+                            eprintln!(
+                                "\n[WARN] Dropped incoming message ({} bytes, max {})",
+                                len, max_len
+                            );
+                            continue;
+                        }
+                    };
+                    let n = buffer.len();
+                    info!("\n[NETWORK] Received encrypted message ({} bytes)", n);
+                    info!("[←] Received {} bytes", n);
 
                     let mut lcg = lcg_clone.lock().unwrap();
                     let start_pos = lcg.position;
 
-                    print!("\\n[DECRYPT]\\n  Cipher:");
+                    info_inline!("\n[DECRYPT]\n  Cipher:");
                     for b in &buffer[..n] {
-                        print!(" {:02x}", b);
+                        info_inline!(" {:02x}", b);
                     }
-                    println!();
+                    info!();
 
-                    print!("  Key:   ");
+                    info_inline!("  Key:   ");
                     let mut decrypted = Vec::with_capacity(n);
 
                     for b in &buffer[..n] {
                         let k = lcg.next_byte();
                         decrypted.push(b ^ k);
-                        print!(" {:02x}", k);
+                        info_inline!(" {:02x}", k);
                     }
-                    println!("  (keystream position: {})", start_pos);
+                    info!("  (keystream position: {})", start_pos);
 
-                    print!("  Plain: ");
+                    info_inline!("  Plain: ");
                     for b in &decrypted {
-                        print!(" {:02x}", b);
+                        info_inline!(" {:02x}", b);
                     }
 
                     let msg = String::from_utf8_lossy(&decrypted);
-                    println!("  → \\\"{}\\\"", msg.trim());
+                    info!("  → \"{}\"", msg.trim());
 
-                    println!(
-                        "\\n[TEST] Round-trip verified: \\\"{}\\\" → encrypt → decrypt → \\\"{}\\\" ✓",
+                    info!(
+                        "\n[TEST] Round-trip verified: \"{}\" → encrypt → decrypt → \"{}\" ✓",
                         msg.trim(),
                         msg.trim()
                     );
 
                     if is_server {
-                        println!("\\n[CLIENT] {}", msg.trim());
+                        println!("\n[CLIENT] {}", msg.trim());
                     } else {
-                        println!("\\n[SERVER] {}", msg.trim());
+                        println!("\n[SERVER] {}", msg.trim());
                     }
                 }
-                Ok(_) => {
-                    println!("Connection closed.");
-                    process::exit(0);
+                Err(_) if reader_shutdown.load(Ordering::SeqCst) => {
+                    process::exit(EXIT_CONNECTION);
                 }
-                Err(_) => {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    println!("Connection closed.");
                     process::exit(0);
                 }
+                Err(e) => fail(EXIT_IO, "Failed to read from peer", e),
             }
         }
     });
@@ -253,47 +893,121 @@ fn handle_connection(mut stream: TcpStream, is_server: bool) {
 
     loop {
         buffer.clear();
-        if handle.read_line(&mut buffer).is_err() {
-            break;
+        match handle.read_line(&mut buffer) {
+            // End of input: hang up so the peer sees a clean close too, and
+            // let the reader report it and exit.
+            Ok(0) => {
+                let _ = stream.shutdown(Shutdown::Both);
+                let _ = reader.join();
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => fail(EXIT_IO, "Failed to read stdin", e),
         }
         let trimmed = buffer.trim();
         if trimmed.is_empty() {
             continue;
         }
+        if trimmed == "/who" {
+            if is_server {
+                println!("[WHO] {}", who_list);
+            } else if let Err(e) = writer.lock().unwrap().write_frame(FRAME_WHO, &[]) {
+                fail(EXIT_IO, "Failed to send", e);
+            } else {
+                *last_sent.lock().unwrap() = Instant::now();
+            }
+            continue;
+        }
 
         let plain_bytes = trimmed.as_bytes();
         let len = plain_bytes.len();
+        if len > opts.max_message_size {
+            eprintln!(
+                "error: Message too long ({} bytes, max {})",
+                len, opts.max_message_size
+            );
+            continue;
+        }
 
         let mut lcg = lcg.lock().unwrap();
         let start_pos = lcg.position;
 
-        println!("\\n\\n[ENCRYPT]");
-        print!("  Plain: ");
+        info!("\n\n[ENCRYPT]");
+        info_inline!("  Plain: ");
         for b in plain_bytes {
-            print!(" {:02x}", b);
+            info_inline!(" {:02x}", b);
         }
-        println!("  (\\\"{}\\\")", trimmed);
+        info!("  (\"{}\")", trimmed);
 
-        print!("  Key:   ");
+        info_inline!("  Key:   ");
         let mut cipher_bytes = Vec::with_capacity(len);
         for b in plain_bytes {
             let k = lcg.next_byte();
-            print!(" {:02x}", k);
+            info_inline!(" {:02x}", k);
             cipher_bytes.push(b ^ k);
         }
-        println!("  (keystream position: {})", start_pos);
+        info!("  (keystream position: {})", start_pos);
 
-        print!("  Cipher:");
+        info_inline!("  Cipher:");
         for b in &cipher_bytes {
-            print!(" {:02x}", b);
+            info_inline!(" {:02x}", b);
         }
-        println!();
+        info!();
+
+        info!("\n[NETWORK] Sending encrypted message ({} bytes)...", len);
+        if let Err(e) = writer
+            .lock()
+            .unwrap()
+            .write_frame(FRAME_CHAT, &cipher_bytes)
+        {
+            fail(EXIT_IO, "Failed to send", e);
+        }
+        *last_sent.lock().unwrap() = Instant::now();
+        info!("[→] Sent {} bytes", len);
+    }
+}
 
-        println!("\\n[NETWORK] Sending encrypted message ({} bytes)...", len);
-        if stream.write_all(&cipher_bytes).is_ok() {
-            println!("[→] Sent {} bytes", len);
-        } else {
-            break;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs both sides of the handshake over loopback and returns the seeds
+    // each side derived, or None when `address` cannot be bound.
+    fn handshake_over(address: &str, opts: ChatOptions) -> Option<(u64, u64)> {
+        let listener = match TcpListener::bind(address) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("skipping: cannot bind {}: {}", address, e);
+                return None;
+            }
+        };
+        let local = listener.local_addr().unwrap();
+        let opts = Arc::new(opts);
+        let server_opts = Arc::clone(&opts);
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            negotiate_seed(&mut stream, true, &server_opts)
+        });
+        let mut stream = TcpStream::connect(local).unwrap();
+        let client_seed = negotiate_seed(&mut stream, false, &opts);
+        Some((server.join().unwrap(), client_seed))
+    }
+
+    #[test]
+    fn handshake_over_ipv6_loopback() {
+        QUIET.store(true, Ordering::Relaxed);
+        // Hosts without IPv6 (some containers and CI runners) cannot bind
+        // [::1], so the test only checks the exchange where it can run.
+        if let Some((server, client)) = handshake_over("[::1]:0", parse_options(&[])) {
+            assert_eq!(server, client);
         }
     }
+
+    #[test]
+    fn handshake_with_password_agrees() {
+        QUIET.store(true, Ordering::Relaxed);
+        let args = ["--password".to_string(), "hunter2".to_string()];
+        let (server, client) = handshake_over("127.0.0.1:0", parse_options(&args)).unwrap();
+        assert_eq!(server, client);
+    }
 }
@@ -0,0 +1,1375 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+#[derive(Clone)]
+pub struct Grid {
+    pub cells: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+    // Toroidal movement: stepping off an edge re-enters on the opposite side.
+    pub wrap: bool,
+}
+
+pub type Point = (usize, usize);
+
+pub const TIME_CHECK_INTERVAL: usize = 1024;
+
+pub struct PathResult {
+    pub path: Vec<(usize, usize)>,
+    pub total_cost: u32,
+}
+
+pub struct SearchOutcome {
+    pub result: Option<PathResult>,
+    pub explored: usize,
+    pub timed_out: bool,
+    pub diagnostics: Option<SearchDiagnostics>,
+}
+
+pub struct SearchDiagnostics {
+    pub max_heap: usize,
+    pub relaxations: usize,
+    pub depth: usize,
+    // Rank at which each cell was settled (u32::MAX if never); only
+    // recorded by dijkstra_min_traced.
+    pub settle_order: Option<Vec<u32>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CostMode {
+    Cell,
+    Gradient,
+}
+
+impl CostMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "cell" => Some(CostMode::Cell),
+            "gradient" => Some(CostMode::Gradient),
+            _ => None,
+        }
+    }
+}
+
+// Largest accepted --uphill-penalty or direction multiplier. Path sums
+// saturate at COST_CAP either way; the bound keeps results meaningful.
+pub const MAX_COST_FACTOR: f64 = 1000.0;
+
+#[derive(Clone, Copy)]
+pub struct CostModel {
+    pub mode: CostMode,
+    pub uphill_penalty: f64,
+    // Multiplier per move direction, indexed like move_direction:
+    // east, west, south, north.
+    pub direction_cost: [f64; 4],
+}
+
+impl CostModel {
+    pub fn step_cost(&self, grid: &Grid, from: (usize, usize), to: (usize, usize)) -> u32 {
+        let from_value = grid.get(from.0, from.1);
+        let to_value = grid.get(to.0, to.1);
+        let base = match self.mode {
+            CostMode::Cell => to_value as u32,
+            CostMode::Gradient => (to_value as i32 - from_value as i32).unsigned_abs(),
+        };
+        let mut factor = self.direction_cost[move_direction(from, to)];
+        if to_value > from_value {
+            factor *= self.uphill_penalty;
+        }
+        if factor == 1.0 {
+            base
+        } else {
+            (base as f64 * factor).round() as u32
+        }
+    }
+
+    // True when no step can cost less than its base value, which the
+    // diagonal lower bound relies on.
+    pub fn never_discounts(&self) -> bool {
+        self.uphill_penalty >= 1.0 && self.direction_cost.iter().all(|&m| m >= 1.0)
+    }
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            mode: CostMode::Cell,
+            uphill_penalty: 1.0,
+            direction_cost: [1.0; 4],
+        }
+    }
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, cells: Vec<u8>) -> Self {
+        Grid {
+            cells,
+            width,
+            height,
+            wrap: false,
+        }
+    }
+
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.cells[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        let idx = self.index(x, y);
+        self.cells[idx] = value;
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, u8> {
+        self.cells.chunks(self.width)
+    }
+
+    pub fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        if self.wrap {
+            let (w, h) = (self.width, self.height);
+            let mut result = Vec::new();
+            for n in [
+                ((x + w - 1) % w, y),
+                ((x + 1) % w, y),
+                (x, (y + h - 1) % h),
+                (x, (y + 1) % h),
+            ] {
+                if n != (x, y) && !result.contains(&n) {
+                    result.push(n);
+                }
+            }
+            return result;
+        }
+
+        let mut result = Vec::new();
+        if x > 0 {
+            result.push((x - 1, y));
+        }
+        if x < self.width - 1 {
+            result.push((x + 1, y));
+        }
+        if y > 0 {
+            result.push((x, y - 1));
+        }
+        if y < self.height - 1 {
+            result.push((x, y + 1));
+        }
+        result
+    }
+}
+
+// Path costs saturate one below u32::MAX, which the searches reserve as
+// the "not reached yet" distance.
+pub const COST_CAP: u32 = u32::MAX - 1;
+
+pub fn add_cost(a: u32, b: u32) -> u32 {
+    a.saturating_add(b).min(COST_CAP)
+}
+
+pub fn deadline_passed(deadline: Option<Instant>, explored: usize) -> bool {
+    explored.is_multiple_of(TIME_CHECK_INTERVAL) && deadline.is_some_and(|d| Instant::now() >= d)
+}
+
+// With a goal value the search stops at the cheapest cell holding that
+// value instead of the bottom-right corner.
+pub fn dijkstra_min(
+    grid: &Grid,
+    model: &CostModel,
+    goal_value: Option<u8>,
+    deadline: Option<Instant>,
+) -> SearchOutcome {
+    dijkstra_min_search(grid, model, goal_value, deadline, false)
+}
+
+// Same search, also recording the settle order for the heatmap.
+pub fn dijkstra_min_traced(
+    grid: &Grid,
+    model: &CostModel,
+    goal_value: Option<u8>,
+    deadline: Option<Instant>,
+) -> SearchOutcome {
+    dijkstra_min_search(grid, model, goal_value, deadline, true)
+}
+
+fn dijkstra_min_search(
+    grid: &Grid,
+    model: &CostModel,
+    goal_value: Option<u8>,
+    deadline: Option<Instant>,
+    record_order: bool,
+) -> SearchOutcome {
+    let mut heap = BinaryHeap::new();
+    let mut dist = vec![u32::MAX; grid.width * grid.height];
+    let mut parent = vec![None; grid.width * grid.height];
+    let mut explored = 0;
+    let mut max_heap = 1;
+    let mut relaxations = 0;
+    let mut settle_order = record_order.then(|| vec![u32::MAX; grid.width * grid.height]);
+
+    heap.push(Reverse((0u32, 0usize, 0usize)));
+    dist[grid.index(0, 0)] = 0;
+
+    while let Some(Reverse((cost, x, y))) = heap.pop() {
+        if cost > dist[grid.index(x, y)] {
+            continue;
+        }
+        if let Some(order) = settle_order.as_mut() {
+            order[grid.index(x, y)] = explored as u32;
+        }
+        explored += 1;
+        if deadline_passed(deadline, explored) {
+            return SearchOutcome {
+                result: None,
+                explored,
+                timed_out: true,
+                diagnostics: None,
+            };
+        }
+
+        let at_goal = match goal_value {
+            Some(v) => grid.get(x, y) == v,
+            None => (x, y) == (grid.width - 1, grid.height - 1),
+        };
+        if at_goal {
+            let result = reconstruct_path(grid, &parent, &dist, (x, y));
+            let depth = result.path.len() - 1;
+            return SearchOutcome {
+                result: Some(result),
+                explored,
+                timed_out: false,
+                diagnostics: Some(SearchDiagnostics {
+                    max_heap,
+                    relaxations,
+                    depth,
+                    settle_order,
+                }),
+            };
+        }
+
+        for (nx, ny) in grid.neighbors(x, y) {
+            let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+            if new_cost < dist[grid.index(nx, ny)] {
+                dist[grid.index(nx, ny)] = new_cost;
+                parent[grid.index(nx, ny)] = Some((x, y));
+                heap.push(Reverse((new_cost, nx, ny)));
+                relaxations += 1;
+                max_heap = max_heap.max(heap.len());
+            }
+        }
+    }
+
+    SearchOutcome {
+        result: None,
+        explored,
+        timed_out: false,
+        diagnostics: Some(SearchDiagnostics {
+            max_heap,
+            relaxations,
+            depth: 0,
+            settle_order,
+        }),
+    }
+}
+
+// Cheapest route between two arbitrary cells, used to chain waypoint
+// segments together.
+pub fn dijkstra_between(
+    grid: &Grid,
+    model: &CostModel,
+    start: Point,
+    goal: Point,
+    deadline: Option<Instant>,
+) -> SearchOutcome {
+    let mut heap = BinaryHeap::new();
+    let mut dist = vec![u32::MAX; grid.width * grid.height];
+    let mut parent = vec![None; grid.width * grid.height];
+    let mut explored = 0;
+
+    heap.push(Reverse((0u32, start.0, start.1)));
+    dist[grid.index(start.0, start.1)] = 0;
+
+    while let Some(Reverse((cost, x, y))) = heap.pop() {
+        if cost > dist[grid.index(x, y)] {
+            continue;
+        }
+        explored += 1;
+        if deadline_passed(deadline, explored) {
+            return SearchOutcome {
+                result: None,
+                explored,
+                timed_out: true,
+                diagnostics: None,
+            };
+        }
+
+        if (x, y) == goal {
+            return SearchOutcome {
+                result: Some(reconstruct_path(grid, &parent, &dist, goal)),
+                explored,
+                timed_out: false,
+                diagnostics: None,
+            };
+        }
+
+        for (nx, ny) in grid.neighbors(x, y) {
+            let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+            if new_cost < dist[grid.index(nx, ny)] {
+                dist[grid.index(nx, ny)] = new_cost;
+                parent[grid.index(nx, ny)] = Some((x, y));
+                heap.push(Reverse((new_cost, nx, ny)));
+            }
+        }
+    }
+
+    SearchOutcome {
+        result: None,
+        explored,
+        timed_out: false,
+        diagnostics: None,
+    }
+}
+
+// Paused state of the minimum search. Checkpoint files hold a header with
+// the grid size and a fingerprint of the grid and cost model, then 8 bytes
+// per cell (distance and parent) and 8 bytes per queued node, all
+// little-endian. Sizes are stored as u32, so grids are limited to 2^32
+// cells.
+pub struct SearchState {
+    pub dist: Vec<u32>,
+    pub parent: Vec<Option<Point>>,
+    pub heap: Vec<(u32, usize, usize)>,
+    pub explored: usize,
+}
+
+pub enum Checkpointed {
+    Finished(SearchOutcome),
+    Interrupted(SearchState),
+}
+
+const CHECKPOINT_MAGIC: &[u8; 4] = b"HXCK";
+const NO_PARENT: u32 = u32::MAX;
+
+fn fingerprint(grid: &Grid, model: &CostModel) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mode = match model.mode {
+        CostMode::Cell => 0u8,
+        CostMode::Gradient => 1u8,
+    };
+    let header = [grid.wrap as u8, mode];
+    let factors: Vec<u8> = std::iter::once(model.uphill_penalty)
+        .chain(model.direction_cost)
+        .flat_map(f64::to_le_bytes)
+        .collect();
+    for &b in grid.cells.iter().chain(&header).chain(&factors) {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let chunk = self
+            .bytes
+            .get(self.pos..self.pos + n)
+            .ok_or("checkpoint is truncated")?;
+        self.pos += n;
+        Ok(chunk)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.take(8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    }
+}
+
+impl SearchState {
+    pub fn start(grid: &Grid) -> Self {
+        let mut dist = vec![u32::MAX; grid.width * grid.height];
+        dist[0] = 0;
+        SearchState {
+            dist,
+            parent: vec![None; grid.width * grid.height],
+            heap: vec![(0, 0, 0)],
+            explored: 0,
+        }
+    }
+
+    pub fn to_bytes(&self, grid: &Grid, model: &CostModel) -> Vec<u8> {
+        let mut out = Vec::with_capacity(32 + self.dist.len() * 8 + self.heap.len() * 8);
+        out.extend_from_slice(CHECKPOINT_MAGIC);
+        out.extend_from_slice(&(grid.width as u32).to_le_bytes());
+        out.extend_from_slice(&(grid.height as u32).to_le_bytes());
+        out.extend_from_slice(&fingerprint(grid, model).to_le_bytes());
+        out.extend_from_slice(&(self.explored as u64).to_le_bytes());
+        for (&d, p) in self.dist.iter().zip(&self.parent) {
+            let p = p.map_or(NO_PARENT, |(x, y)| grid.index(x, y) as u32);
+            out.extend_from_slice(&d.to_le_bytes());
+            out.extend_from_slice(&p.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.heap.len() as u32).to_le_bytes());
+        for &(cost, x, y) in &self.heap {
+            out.extend_from_slice(&cost.to_le_bytes());
+            out.extend_from_slice(&(grid.index(x, y) as u32).to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8], grid: &Grid, model: &CostModel) -> Result<Self, String> {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        if reader.take(4)? != CHECKPOINT_MAGIC {
+            return Err("not a hexpath checkpoint".to_string());
+        }
+        let width = reader.u32()? as usize;
+        let height = reader.u32()? as usize;
+        if (width, height) != (grid.width, grid.height) {
+            return Err(format!(
+                "checkpoint is for a {}×{} grid, map is {}×{}",
+                width, height, grid.width, grid.height
+            ));
+        }
+        if reader.u64()? != fingerprint(grid, model) {
+            return Err("checkpoint was made with a different map or cost model".to_string());
+        }
+        let explored = reader.u64()? as usize;
+
+        let cells = width * height;
+        let to_point = |idx: u32| -> Result<Point, String> {
+            let idx = idx as usize;
+            if idx >= cells {
+                return Err("checkpoint refers to a cell outside the grid".to_string());
+            }
+            Ok((idx % width, idx / width))
+        };
+        let mut dist = Vec::with_capacity(cells);
+        let mut parent = Vec::with_capacity(cells);
+        for _ in 0..cells {
+            dist.push(reader.u32()?);
+            parent.push(match reader.u32()? {
+                NO_PARENT => None,
+                idx => Some(to_point(idx)?),
+            });
+        }
+        let queued = reader.u32()? as usize;
+        let mut heap = Vec::with_capacity(queued.min(cells * 4));
+        for _ in 0..queued {
+            let cost = reader.u32()?;
+            let (x, y) = to_point(reader.u32()?)?;
+            heap.push((cost, x, y));
+        }
+        Ok(SearchState {
+            dist,
+            parent,
+            heap,
+            explored,
+        })
+    }
+}
+
+// The standard minimum search, but resumable: when `interrupted` is raised
+// the loop stops and hands back its state instead of a result.
+pub fn dijkstra_min_checkpointed(
+    grid: &Grid,
+    model: &CostModel,
+    state: SearchState,
+    interrupted: &AtomicBool,
+    deadline: Option<Instant>,
+) -> Checkpointed {
+    let SearchState {
+        mut dist,
+        mut parent,
+        heap,
+        mut explored,
+    } = state;
+    let mut heap: BinaryHeap<_> = heap.into_iter().map(Reverse).collect();
+    let goal = (grid.width - 1, grid.height - 1);
+
+    while !interrupted.load(Ordering::SeqCst) {
+        let Some(Reverse((cost, x, y))) = heap.pop() else {
+            break;
+        };
+        if cost > dist[grid.index(x, y)] {
+            continue;
+        }
+        explored += 1;
+        if deadline_passed(deadline, explored) {
+            return Checkpointed::Finished(SearchOutcome {
+                result: None,
+                explored,
+                timed_out: true,
+                diagnostics: None,
+            });
+        }
+
+        if (x, y) == goal {
+            return Checkpointed::Finished(SearchOutcome {
+                result: Some(reconstruct_path(grid, &parent, &dist, goal)),
+                explored,
+                timed_out: false,
+                diagnostics: None,
+            });
+        }
+
+        for (nx, ny) in grid.neighbors(x, y) {
+            let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+            if new_cost < dist[grid.index(nx, ny)] {
+                dist[grid.index(nx, ny)] = new_cost;
+                parent[grid.index(nx, ny)] = Some((x, y));
+                heap.push(Reverse((new_cost, nx, ny)));
+            }
+        }
+    }
+
+    if interrupted.load(Ordering::SeqCst) {
+        return Checkpointed::Interrupted(SearchState {
+            dist,
+            parent,
+            heap: heap.into_iter().map(|Reverse(entry)| entry).collect(),
+            explored,
+        });
+    }
+    Checkpointed::Finished(SearchOutcome {
+        result: None,
+        explored,
+        timed_out: false,
+        diagnostics: None,
+    })
+}
+
+// `reached` is tracked apart from `dist` so that zero-cost steps (common
+// under the gradient model) still count as reaching a cell.
+pub fn dijkstra_max(grid: &Grid, model: &CostModel, deadline: Option<Instant>) -> SearchOutcome {
+    let mut heap = BinaryHeap::new();
+    let mut dist = vec![0u32; grid.width * grid.height];
+    let mut parent = vec![None; grid.width * grid.height];
+    let mut visited = vec![false; grid.width * grid.height];
+    let mut reached = vec![false; grid.width * grid.height];
+    let mut explored = 0;
+
+    heap.push((0u32, 0usize, 0usize));
+    dist[grid.index(0, 0)] = 0;
+    reached[grid.index(0, 0)] = true;
+
+    while let Some((cost, x, y)) = heap.pop() {
+        if visited[grid.index(x, y)] {
+            continue;
+        }
+        visited[grid.index(x, y)] = true;
+        explored += 1;
+        if deadline_passed(deadline, explored) {
+            return SearchOutcome {
+                result: None,
+                explored,
+                timed_out: true,
+                diagnostics: None,
+            };
+        }
+
+        if (x, y) == (grid.width - 1, grid.height - 1) {
+            return SearchOutcome {
+                result: Some(reconstruct_path(
+                    grid,
+                    &parent,
+                    &dist,
+                    (grid.width - 1, grid.height - 1),
+                )),
+                explored,
+                timed_out: false,
+                diagnostics: None,
+            };
+        }
+
+        for (nx, ny) in grid.neighbors(x, y) {
+            if !visited[grid.index(nx, ny)] {
+                let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+                let idx = grid.index(nx, ny);
+                if !reached[idx] || new_cost > dist[idx] {
+                    reached[idx] = true;
+                    dist[idx] = new_cost;
+                    parent[idx] = Some((x, y));
+                    heap.push((new_cost, nx, ny));
+                }
+            }
+        }
+    }
+
+    SearchOutcome {
+        result: None,
+        explored,
+        timed_out: false,
+        diagnostics: None,
+    }
+}
+
+struct LongestSearch<'a> {
+    grid: &'a Grid,
+    model: &'a CostModel,
+    max_in: Vec<u32>,
+    visited: Vec<bool>,
+    path: Vec<Point>,
+    floor: u32,
+    best: Option<PathResult>,
+    explored: usize,
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+impl LongestSearch<'_> {
+    // Most the path could still gain: every unvisited cell reachable from
+    // (x, y), entered at its most expensive step. None when the goal is
+    // cut off.
+    fn optimistic_gain(&self, x: usize, y: usize) -> Option<u32> {
+        let goal = (self.grid.width - 1, self.grid.height - 1);
+        let mut seen = self.visited.clone();
+        let mut queue = VecDeque::new();
+        queue.push_back((x, y));
+        let mut gain = 0;
+        let mut reaches_goal = (x, y) == goal;
+        while let Some((cx, cy)) = queue.pop_front() {
+            for (nx, ny) in self.grid.neighbors(cx, cy) {
+                let idx = self.grid.index(nx, ny);
+                if !seen[idx] {
+                    seen[idx] = true;
+                    gain = add_cost(self.max_in[idx], gain);
+                    reaches_goal |= (nx, ny) == goal;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        reaches_goal.then_some(gain)
+    }
+
+    fn visit(&mut self, x: usize, y: usize, cost: u32) {
+        if self.timed_out {
+            return;
+        }
+        self.explored += 1;
+        if deadline_passed(self.deadline, self.explored) {
+            self.timed_out = true;
+            return;
+        }
+
+        let bound = match self.optimistic_gain(x, y) {
+            Some(gain) => add_cost(cost, gain),
+            None => return,
+        };
+        let beaten = match &self.best {
+            Some(best) => bound <= best.total_cost,
+            None => bound < self.floor,
+        };
+        if beaten {
+            return;
+        }
+
+        if (x, y) == (self.grid.width - 1, self.grid.height - 1) {
+            let improves = match &self.best {
+                Some(best) => cost > best.total_cost,
+                None => cost >= self.floor,
+            };
+            if improves {
+                self.best = Some(PathResult {
+                    path: self.path.clone(),
+                    total_cost: cost,
+                });
+            }
+            return;
+        }
+
+        for (nx, ny) in self.grid.neighbors(x, y) {
+            let idx = self.grid.index(nx, ny);
+            if self.visited[idx] {
+                continue;
+            }
+            let step = self.model.step_cost(self.grid, (x, y), (nx, ny));
+            self.visited[idx] = true;
+            self.path.push((nx, ny));
+            self.visit(nx, ny, add_cost(cost, step));
+            self.path.pop();
+            self.visited[idx] = false;
+        }
+    }
+}
+
+// Exact longest simple path by depth-first branch and bound. Partial paths
+// whose optimistic total cannot reach `floor` (or beat the best path found
+// so far) are pruned; that only saves time as long as `floor` does not
+// exceed the true maximum. Exponential, so meant for small grids.
+pub fn longest_path_exact(
+    grid: &Grid,
+    model: &CostModel,
+    floor: u32,
+    deadline: Option<Instant>,
+) -> SearchOutcome {
+    let mut max_in = vec![0u32; grid.width * grid.height];
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            for (nx, ny) in grid.neighbors(x, y) {
+                let idx = grid.index(nx, ny);
+                max_in[idx] = max_in[idx].max(model.step_cost(grid, (x, y), (nx, ny)));
+            }
+        }
+    }
+
+    let mut visited = vec![false; grid.width * grid.height];
+    visited[0] = true;
+    let mut search = LongestSearch {
+        grid,
+        model,
+        max_in,
+        visited,
+        path: vec![(0, 0)],
+        floor,
+        best: None,
+        explored: 0,
+        deadline,
+        timed_out: false,
+    };
+    search.visit(0, 0, 0);
+
+    SearchOutcome {
+        result: if search.timed_out { None } else { search.best },
+        explored: search.explored,
+        timed_out: search.timed_out,
+        diagnostics: None,
+    }
+}
+
+// A jump of more than one cell is a wrap-around move, which travels the
+// opposite way to what the coordinates suggest.
+pub fn move_direction(from: (usize, usize), to: (usize, usize)) -> usize {
+    let dir = if to.0 > from.0 {
+        0
+    } else if to.0 < from.0 {
+        1
+    } else if to.1 > from.1 {
+        2
+    } else {
+        3
+    };
+    if from.0.abs_diff(to.0) > 1 || from.1.abs_diff(to.1) > 1 {
+        dir ^ 1
+    } else {
+        dir
+    }
+}
+
+pub fn count_turns(path: &[(usize, usize)]) -> usize {
+    path.windows(3)
+        .filter(|w| move_direction(w[0], w[1]) != move_direction(w[1], w[2]))
+        .count()
+}
+
+// Any route to the goal crosses every anti-diagonal x + y = d at least
+// once, so the cheapest cell on each one bounds the plain cell cost.
+pub fn diagonal_lower_bound(grid: &Grid) -> u32 {
+    let mut cheapest = vec![u32::MAX; grid.width + grid.height - 1];
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let d = x + y;
+            cheapest[d] = cheapest[d].min(grid.get(x, y) as u32);
+        }
+    }
+    cheapest.iter().skip(1).sum()
+}
+
+// A* toward the bottom-right corner. The heuristic sums the cheapest cell
+// on each remaining anti-diagonal (see diagonal_lower_bound); it is only
+// admissible when every step costs at least the destination value, so
+// other cost models (and wrapped grids, where a route can skip diagonals)
+// fall back to a zero heuristic.
+pub fn astar_min(grid: &Grid, model: &CostModel, deadline: Option<Instant>) -> SearchOutcome {
+    let goal = (grid.width - 1, grid.height - 1);
+    let diagonals = grid.width + grid.height - 1;
+    let mut remaining = vec![0u32; diagonals + 1];
+    if model.mode == CostMode::Cell && model.never_discounts() && !grid.wrap {
+        let mut cheapest = vec![u32::MAX; diagonals];
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let d = x + y;
+                cheapest[d] = cheapest[d].min(grid.get(x, y) as u32);
+            }
+        }
+        for d in (1..diagonals).rev() {
+            remaining[d] = remaining[d + 1] + cheapest[d];
+        }
+    }
+    let heuristic = |x: usize, y: usize| remaining[x + y + 1];
+
+    let mut heap = BinaryHeap::new();
+    let mut dist = vec![u32::MAX; grid.width * grid.height];
+    let mut parent = vec![None; grid.width * grid.height];
+    let mut closed = vec![false; grid.width * grid.height];
+    let mut explored = 0;
+
+    heap.push(Reverse((heuristic(0, 0), 0usize, 0usize)));
+    dist[grid.index(0, 0)] = 0;
+
+    while let Some(Reverse((_, x, y))) = heap.pop() {
+        if closed[grid.index(x, y)] {
+            continue;
+        }
+        closed[grid.index(x, y)] = true;
+        explored += 1;
+        if deadline_passed(deadline, explored) {
+            return SearchOutcome {
+                result: None,
+                explored,
+                timed_out: true,
+                diagnostics: None,
+            };
+        }
+
+        if (x, y) == goal {
+            return SearchOutcome {
+                result: Some(reconstruct_path(grid, &parent, &dist, goal)),
+                explored,
+                timed_out: false,
+                diagnostics: None,
+            };
+        }
+
+        let cost = dist[grid.index(x, y)];
+        for (nx, ny) in grid.neighbors(x, y) {
+            let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+            if new_cost < dist[grid.index(nx, ny)] {
+                dist[grid.index(nx, ny)] = new_cost;
+                parent[grid.index(nx, ny)] = Some((x, y));
+                heap.push(Reverse((add_cost(new_cost, heuristic(nx, ny)), nx, ny)));
+            }
+        }
+    }
+
+    SearchOutcome {
+        result: None,
+        explored,
+        timed_out: false,
+        diagnostics: None,
+    }
+}
+
+// Searches over (x, y, arrival direction) states, so the dist/parent
+// buffers are four times the size of the plain search's.
+pub fn dijkstra_min_turns(
+    grid: &Grid,
+    model: &CostModel,
+    turn_penalty: u32,
+    deadline: Option<Instant>,
+) -> SearchOutcome {
+    let goal = (grid.width - 1, grid.height - 1);
+    if goal == (0, 0) {
+        return SearchOutcome {
+            result: Some(PathResult {
+                path: vec![(0, 0)],
+                total_cost: 0,
+            }),
+            explored: 1,
+            timed_out: false,
+            diagnostics: None,
+        };
+    }
+
+    let states = grid.width * grid.height * 4;
+    let mut heap = BinaryHeap::new();
+    let mut dist = vec![u32::MAX; states];
+    let mut parent: Vec<Option<usize>> = vec![None; states];
+    let mut explored = 0;
+
+    for (nx, ny) in grid.neighbors(0, 0) {
+        let dir = move_direction((0, 0), (nx, ny));
+        let state = grid.index(nx, ny) * 4 + dir;
+        let cost = model.step_cost(grid, (0, 0), (nx, ny));
+        dist[state] = cost;
+        heap.push(Reverse((cost, nx, ny, dir)));
+    }
+
+    while let Some(Reverse((cost, x, y, dir))) = heap.pop() {
+        let state = grid.index(x, y) * 4 + dir;
+        if cost > dist[state] {
+            continue;
+        }
+        explored += 1;
+        if deadline_passed(deadline, explored) {
+            return SearchOutcome {
+                result: None,
+                explored,
+                timed_out: true,
+                diagnostics: None,
+            };
+        }
+
+        if (x, y) == goal {
+            let mut path = Vec::new();
+            let mut current = Some(state);
+            while let Some(s) = current {
+                let idx = s / 4;
+                path.push((idx % grid.width, idx / grid.width));
+                current = parent[s];
+            }
+            path.push((0, 0));
+            path.reverse();
+            return SearchOutcome {
+                result: Some(PathResult {
+                    path,
+                    total_cost: cost,
+                }),
+                explored,
+                timed_out: false,
+                diagnostics: None,
+            };
+        }
+
+        for (nx, ny) in grid.neighbors(x, y) {
+            let next_dir = move_direction((x, y), (nx, ny));
+            let mut new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+            if next_dir != dir {
+                new_cost = add_cost(new_cost, turn_penalty);
+            }
+            let next_state = grid.index(nx, ny) * 4 + next_dir;
+            if new_cost < dist[next_state] {
+                dist[next_state] = new_cost;
+                parent[next_state] = Some(state);
+                heap.push(Reverse((new_cost, nx, ny, next_dir)));
+            }
+        }
+    }
+
+    SearchOutcome {
+        result: None,
+        explored,
+        timed_out: false,
+        diagnostics: None,
+    }
+}
+
+pub fn dijkstra_bidirectional(
+    grid: &Grid,
+    model: &CostModel,
+    deadline: Option<Instant>,
+) -> (SearchOutcome, Option<Point>) {
+    let goal = (grid.width - 1, grid.height - 1);
+    let mut fwd_heap = BinaryHeap::new();
+    let mut bwd_heap = BinaryHeap::new();
+    let mut fwd_dist = vec![u32::MAX; grid.width * grid.height];
+    let mut bwd_dist = vec![u32::MAX; grid.width * grid.height];
+    let mut fwd_parent: Vec<Option<(usize, usize)>> = vec![None; grid.width * grid.height];
+    let mut bwd_parent: Vec<Option<(usize, usize)>> = vec![None; grid.width * grid.height];
+
+    fwd_heap.push(Reverse((0u32, 0usize, 0usize)));
+    fwd_dist[grid.index(0, 0)] = 0;
+    bwd_heap.push(Reverse((0u32, goal.0, goal.1)));
+    bwd_dist[grid.index(goal.0, goal.1)] = 0;
+
+    let mut explored = 0;
+
+    let mut best = if goal == (0, 0) {
+        Some((0u32, (0, 0)))
+    } else {
+        None
+    };
+
+    loop {
+        let fwd_top = fwd_heap.peek().map(|Reverse((c, _, _))| *c);
+        let bwd_top = bwd_heap.peek().map(|Reverse((c, _, _))| *c);
+        let (Some(f), Some(b)) = (fwd_top, bwd_top) else {
+            break;
+        };
+        if let Some((cost, _)) = best {
+            if f as u64 + b as u64 >= cost as u64 {
+                break;
+            }
+        }
+
+        if f <= b {
+            let Reverse((cost, x, y)) = fwd_heap.pop().unwrap();
+            if cost > fwd_dist[grid.index(x, y)] {
+                continue;
+            }
+            explored += 1;
+            if deadline_passed(deadline, explored) {
+                let outcome = SearchOutcome {
+                    result: None,
+                    explored,
+                    timed_out: true,
+                    diagnostics: None,
+                };
+                return (outcome, None);
+            }
+            for (nx, ny) in grid.neighbors(x, y) {
+                let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+                if new_cost < fwd_dist[grid.index(nx, ny)] {
+                    fwd_dist[grid.index(nx, ny)] = new_cost;
+                    fwd_parent[grid.index(nx, ny)] = Some((x, y));
+                    fwd_heap.push(Reverse((new_cost, nx, ny)));
+                }
+                if bwd_dist[grid.index(nx, ny)] != u32::MAX {
+                    let total =
+                        add_cost(fwd_dist[grid.index(nx, ny)], bwd_dist[grid.index(nx, ny)]);
+                    if best.is_none_or(|(c, _)| total < c) {
+                        best = Some((total, (nx, ny)));
+                    }
+                }
+            }
+        } else {
+            let Reverse((cost, x, y)) = bwd_heap.pop().unwrap();
+            if cost > bwd_dist[grid.index(x, y)] {
+                continue;
+            }
+            explored += 1;
+            if deadline_passed(deadline, explored) {
+                let outcome = SearchOutcome {
+                    result: None,
+                    explored,
+                    timed_out: true,
+                    diagnostics: None,
+                };
+                return (outcome, None);
+            }
+            for (nx, ny) in grid.neighbors(x, y) {
+                let new_cost = add_cost(cost, model.step_cost(grid, (nx, ny), (x, y)));
+                if new_cost < bwd_dist[grid.index(nx, ny)] {
+                    bwd_dist[grid.index(nx, ny)] = new_cost;
+                    bwd_parent[grid.index(nx, ny)] = Some((x, y));
+                    bwd_heap.push(Reverse((new_cost, nx, ny)));
+                }
+                if fwd_dist[grid.index(nx, ny)] != u32::MAX {
+                    let total =
+                        add_cost(fwd_dist[grid.index(nx, ny)], bwd_dist[grid.index(nx, ny)]);
+                    if best.is_none_or(|(c, _)| total < c) {
+                        best = Some((total, (nx, ny)));
+                    }
+                }
+            }
+        }
+    }
+
+    let Some((total_cost, meet)) = best else {
+        let outcome = SearchOutcome {
+            result: None,
+            explored,
+            timed_out: false,
+            diagnostics: None,
+        };
+        return (outcome, None);
+    };
+
+    let mut path = Vec::new();
+    let mut current = Some(meet);
+    while let Some((x, y)) = current {
+        path.push((x, y));
+        current = fwd_parent[grid.index(x, y)];
+    }
+    path.reverse();
+
+    let mut current = bwd_parent[grid.index(meet.0, meet.1)];
+    while let Some((x, y)) = current {
+        path.push((x, y));
+        current = bwd_parent[grid.index(x, y)];
+    }
+
+    let outcome = SearchOutcome {
+        result: Some(PathResult { path, total_cost }),
+        explored,
+        timed_out: false,
+        diagnostics: None,
+    };
+    (outcome, Some(meet))
+}
+
+pub enum OptimalCount {
+    Unreachable,
+    Paths(u64),
+    // A zero-cost loop sits on an optimal route, so there are infinitely
+    // many optimal walks.
+    Unbounded,
+}
+
+// Counts minimum-cost routes to the bottom-right corner over the graph of
+// "tight" steps (dist[u] + cost == dist[v]). Counting in topological order
+// rather than settle order keeps zero-cost steps correct; the count
+// saturates at u64::MAX.
+pub fn count_optimal_paths(grid: &Grid, model: &CostModel) -> OptimalCount {
+    let cells = grid.width * grid.height;
+    let goal = grid.index(grid.width - 1, grid.height - 1);
+    let mut dist = vec![u32::MAX; cells];
+    let mut heap = BinaryHeap::new();
+    dist[0] = 0;
+    heap.push(Reverse((0u32, 0usize, 0usize)));
+    while let Some(Reverse((cost, x, y))) = heap.pop() {
+        if cost > dist[grid.index(x, y)] {
+            continue;
+        }
+        for (nx, ny) in grid.neighbors(x, y) {
+            let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+            if new_cost < dist[grid.index(nx, ny)] {
+                dist[grid.index(nx, ny)] = new_cost;
+                heap.push(Reverse((new_cost, nx, ny)));
+            }
+        }
+    }
+    if dist[goal] == u32::MAX {
+        return OptimalCount::Unreachable;
+    }
+
+    let point = |idx: usize| (idx % grid.width, idx / grid.width);
+    let tight = |from: usize, to: usize| {
+        dist[from] != u32::MAX
+            && add_cost(dist[from], model.step_cost(grid, point(from), point(to))) == dist[to]
+    };
+
+    // Only cells that lie on some optimal route matter.
+    let mut relevant = vec![false; cells];
+    let mut stack = vec![goal];
+    relevant[goal] = true;
+    while let Some(v) = stack.pop() {
+        let (x, y) = point(v);
+        for (nx, ny) in grid.neighbors(x, y) {
+            let u = grid.index(nx, ny);
+            if !relevant[u] && tight(u, v) {
+                relevant[u] = true;
+                stack.push(u);
+            }
+        }
+    }
+
+    let mut indegree = vec![0usize; cells];
+    for v in (0..cells).filter(|&v| relevant[v]) {
+        let (x, y) = point(v);
+        for (nx, ny) in grid.neighbors(x, y) {
+            let u = grid.index(nx, ny);
+            if relevant[u] && tight(u, v) {
+                indegree[v] += 1;
+            }
+        }
+    }
+
+    let mut count = vec![0u64; cells];
+    count[0] = 1;
+    let mut queue: VecDeque<usize> = (0..cells)
+        .filter(|&v| relevant[v] && indegree[v] == 0)
+        .collect();
+    let mut processed = 0;
+    while let Some(u) = queue.pop_front() {
+        processed += 1;
+        let (x, y) = point(u);
+        for (nx, ny) in grid.neighbors(x, y) {
+            let v = grid.index(nx, ny);
+            if relevant[v] && tight(u, v) {
+                count[v] = count[v].saturating_add(count[u]);
+                indegree[v] -= 1;
+                if indegree[v] == 0 {
+                    queue.push_back(v);
+                }
+            }
+        }
+    }
+
+    if processed < relevant.iter().filter(|&&r| r).count() {
+        OptimalCount::Unbounded
+    } else {
+        OptimalCount::Paths(count[goal])
+    }
+}
+
+pub fn is_reachable(grid: &Grid) -> bool {
+    let goal = (grid.width - 1, grid.height - 1);
+    let mut visited = vec![false; grid.width * grid.height];
+    let mut queue = VecDeque::new();
+
+    visited[0] = true;
+    queue.push_back((0usize, 0usize));
+
+    while let Some((x, y)) = queue.pop_front() {
+        if (x, y) == goal {
+            return true;
+        }
+        for (nx, ny) in grid.neighbors(x, y) {
+            let idx = grid.index(nx, ny);
+            if !visited[idx] {
+                visited[idx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    false
+}
+
+pub fn reconstruct_path(
+    grid: &Grid,
+    parent: &[Option<(usize, usize)>],
+    dist: &[u32],
+    target: Point,
+) -> PathResult {
+    let mut path = Vec::new();
+    let mut current = target;
+
+    while let Some((x, y)) = Some(current) {
+        path.push((x, y));
+
+        if let Some(p) = parent[grid.index(x, y)] {
+            current = p;
+        } else {
+            break;
+        }
+    }
+
+    path.reverse();
+
+    let total_cost = dist[grid.index(target.0, target.1)];
+
+    PathResult { path, total_cost }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(width: usize, cells: &[u8]) -> Grid {
+        Grid::new(width, cells.len() / width, cells.to_vec())
+    }
+
+    fn min_cost(grid: &Grid, model: &CostModel) -> Option<u32> {
+        dijkstra_min(grid, model, None, None)
+            .result
+            .map(|r| r.total_cost)
+    }
+
+    // Deterministic pseudo-random grid so the comparison covers varied
+    // layouts without a dependency on the rand crate.
+    fn scrambled(width: usize, height: usize, mut seed: u64) -> Grid {
+        let mut cells = Vec::with_capacity(width * height);
+        for _ in 0..width * height {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            cells.push((seed >> 56) as u8);
+        }
+        Grid::new(width, height, cells)
+    }
+
+    #[test]
+    fn two_by_two_min_and_max() {
+        let g = grid(2, &[0x00, 0x05, 0x07, 0xFF]);
+        let model = CostModel::default();
+
+        let min = dijkstra_min(&g, &model, None, None).result.unwrap();
+        assert_eq!(min.total_cost, 0x05 + 0xFF);
+        assert_eq!(min.path, vec![(0, 0), (1, 0), (1, 1)]);
+
+        let max = dijkstra_max(&g, &model, None).result.unwrap();
+        assert_eq!(max.total_cost, 0x07 + 0xFF);
+        assert_eq!(max.path, vec![(0, 0), (0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn min_path_goes_around_a_wall() {
+        // The FF column only has a gap on the bottom row.
+        #[rustfmt::skip]
+        let g = grid(3, &[
+            0x00, 0xFF, 0x01,
+            0x01, 0xFF, 0x01,
+            0x01, 0x01, 0xFF,
+        ]);
+        let min = dijkstra_min(&g, &CostModel::default(), None, None)
+            .result
+            .unwrap();
+        assert_eq!(min.path, vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(min.total_cost, 1 + 1 + 1 + 0xFF);
+    }
+
+    #[test]
+    fn gradient_mode_on_a_flat_grid_costs_nothing() {
+        let g = grid(3, &[0x80; 9]);
+        let model = CostModel {
+            mode: CostMode::Gradient,
+            ..CostModel::default()
+        };
+        assert_eq!(min_cost(&g, &model), Some(0));
+        let max = dijkstra_max(&g, &model, None).result.unwrap();
+        assert_eq!(max.total_cost, 0);
+    }
+
+    #[test]
+    fn no_path_to_a_missing_goal_value() {
+        let g = grid(2, &[0x00, 0x05, 0x07, 0xFF]);
+        let outcome = dijkstra_min(&g, &CostModel::default(), Some(0x42), None);
+        assert!(outcome.result.is_none());
+        assert!(!outcome.timed_out);
+    }
+
+    #[test]
+    fn single_cell_grid_is_its_own_goal() {
+        let g = grid(1, &[0x00]);
+        let min = dijkstra_min(&g, &CostModel::default(), None, None)
+            .result
+            .unwrap();
+        assert_eq!(min.path, vec![(0, 0)]);
+        assert_eq!(min.total_cost, 0);
+    }
+
+    fn sample_models() -> [CostModel; 4] {
+        [
+            CostModel::default(),
+            CostModel {
+                mode: CostMode::Gradient,
+                ..CostModel::default()
+            },
+            CostModel {
+                uphill_penalty: 3.0,
+                ..CostModel::default()
+            },
+            CostModel {
+                direction_cost: [1.5, 0.2, 1.0, 4.0],
+                ..CostModel::default()
+            },
+        ]
+    }
+
+    const SAMPLE_SIZES: [(usize, usize); 5] = [(1, 1), (2, 5), (7, 7), (16, 9), (30, 30)];
+
+    #[test]
+    fn bidirectional_matches_dijkstra_min() {
+        let models = sample_models();
+        for (i, &(width, height)) in SAMPLE_SIZES.iter().enumerate() {
+            let g = scrambled(width, height, i as u64 + 1);
+            for model in &models {
+                let (outcome, _) = dijkstra_bidirectional(&g, model, None);
+                let bidirectional = outcome.result.map(|r| r.total_cost);
+                assert_eq!(
+                    bidirectional,
+                    min_cost(&g, model),
+                    "{}x{} grid",
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn astar_matches_dijkstra_min() {
+        let models = sample_models();
+        for (i, &(width, height)) in SAMPLE_SIZES.iter().enumerate() {
+            let g = scrambled(width, height, i as u64 + 100);
+            for model in &models {
+                let astar = astar_min(&g, model, None).result.map(|r| r.total_cost);
+                assert_eq!(astar, min_cost(&g, model), "{}x{} grid", width, height);
+            }
+        }
+    }
+}
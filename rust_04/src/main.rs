@@ -1,10 +1,19 @@
+use rust_04::{
+    add_cost, astar_min, count_optimal_paths, count_turns, diagonal_lower_bound, dijkstra_between,
+    dijkstra_bidirectional, dijkstra_max, dijkstra_min, dijkstra_min_checkpointed,
+    dijkstra_min_traced, dijkstra_min_turns, is_reachable, longest_path_exact, move_direction,
+    Checkpointed, CostMode, CostModel, Grid, OptimalCount, PathResult, Point, SearchDiagnostics,
+    SearchOutcome, SearchState, MAX_COST_FACTOR,
+};
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashSet};
 use std::env;
 use std::fs;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 struct SimplePrng {
     state: u64,
@@ -29,71 +38,124 @@ impl SimplePrng {
     }
 }
 
-#[derive(Clone)]
-struct Grid {
-    cells: Vec<Vec<u8>>,
-    width: usize,
-    height: usize,
+struct GridStats {
+    min: u8,
+    max: u8,
+    avg: f64,
+    // None when the cost model or --wrap makes diagonal_lower_bound unsound.
+    lower_bound: Option<u32>,
 }
 
-struct PathResult {
-    path: Vec<(usize, usize)>,
-    total_cost: u32,
+struct GenOptions {
+    min_value: u8,
+    max_value: u8,
+    corridor: bool,
 }
 
-impl Grid {
-    fn new(cells: Vec<Vec<u8>>) -> Self {
-        let height = cells.len();
-        let width = if height > 0 { cells[0].len() } else { 0 };
-        Grid {
-            cells,
-            width,
-            height,
+impl Default for GenOptions {
+    fn default() -> Self {
+        GenOptions {
+            min_value: 0x01,
+            max_value: 0xFE,
+            corridor: false,
         }
     }
+}
 
-    fn get(&self, x: usize, y: usize) -> u8 {
-        self.cells[y][x]
-    }
+fn generate_map(width: usize, height: usize, options: &GenOptions) -> Grid {
+    let mut rng = SimplePrng::new();
+    let mut grid = Grid::new(width, height, vec![0u8; width * height]);
 
-    fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
-        let mut result = Vec::new();
-        if x > 0 {
-            result.push((x - 1, y));
-        }
-        if x < self.width - 1 {
-            result.push((x + 1, y));
+    for y in 0..height {
+        for x in 0..width {
+            if (x, y) == (0, 0) || (x, y) == (width - 1, height - 1) {
+                continue;
+            }
+            grid.set(x, y, rng.gen_range(options.min_value, options.max_value));
         }
-        if y > 0 {
-            result.push((x, y - 1));
+    }
+
+    grid.set(0, 0, 0x00);
+    grid.set(width - 1, height - 1, 0xFF);
+
+    if options.corridor {
+        let corridor_max = options
+            .max_value
+            .min(options.min_value.saturating_add(0x0F));
+        carve_corridor(&mut grid, &mut rng, options.min_value, corridor_max);
+    }
+
+    grid
+}
+
+fn parse_cell_value(s: &str) -> Option<u8> {
+    let value = if let Some(stripped) = s.strip_prefix("0x") {
+        u32::from_str_radix(stripped, 16).ok()?
+    } else {
+        s.parse::<u32>().ok()?
+    };
+    Some(value.clamp(0x01, 0xFE) as u8)
+}
+
+// Randomized depth-first walk: step to a random unvisited neighbor in any
+// direction and backtrack out of dead ends. The grid is connected, so the
+// walk always reaches the goal; the cells left on the stack form the
+// corridor.
+fn carve_corridor(grid: &mut Grid, rng: &mut SimplePrng, min: u8, max: u8) {
+    let goal = (grid.width - 1, grid.height - 1);
+    let mut visited = vec![false; grid.width * grid.height];
+    let mut stack = vec![(0, 0)];
+    visited[0] = true;
+
+    while let Some(&(x, y)) = stack.last() {
+        if (x, y) == goal {
+            break;
         }
-        if y < self.height - 1 {
-            result.push((x, y + 1));
+        let open: Vec<Point> = grid
+            .neighbors(x, y)
+            .into_iter()
+            .filter(|&(nx, ny)| !visited[grid.index(nx, ny)])
+            .collect();
+        if open.is_empty() {
+            stack.pop();
+            continue;
         }
-        result
+        let next = open[rng.gen_range(0, (open.len() - 1) as u8) as usize];
+        visited[grid.index(next.0, next.1)] = true;
+        stack.push(next);
     }
-}
 
-fn generate_map(width: usize, height: usize) -> Grid {
-    let mut rng = SimplePrng::new();
-    let mut cells = vec![vec![0u8; width]; height];
+    // A 1x1 grid starts on its goal, leaving nothing between the endpoints.
+    for &(x, y) in stack.get(1..stack.len() - 1).unwrap_or(&[]) {
+        grid.set(x, y, rng.gen_range(min, max));
+    }
+}
 
-    cells[0][0] = 0x00;
-    cells[height - 1][width - 1] = 0xFF;
+#[derive(Clone, Copy, PartialEq)]
+enum MapFormat {
+    Hex,
+    Csv,
+}
 
-    for (y, row) in cells.iter_mut().enumerate() {
-        for (x, cell) in row.iter_mut().enumerate() {
-            if (x, y) == (0, 0) || (x, y) == (width - 1, height - 1) {
-                continue;
-            }
-            *cell = rng.gen_range(0x01, 0xFE);
+impl MapFormat {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "hex" => Some(MapFormat::Hex),
+            "csv" => Some(MapFormat::Csv),
+            _ => None,
         }
     }
 
-    Grid::new(cells)
+    fn detect(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".csv") {
+            MapFormat::Csv
+        } else {
+            MapFormat::Hex
+        }
+    }
 }
 
-fn parse_map(path: &str) -> Result<Grid, String> {
+fn parse_map(path: &str, format: MapFormat) -> Result<Grid, String> {
     let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
 
     let mut cells = Vec::new();
@@ -103,12 +165,23 @@ fn parse_map(path: &str) -> Result<Grid, String> {
             continue;
         }
 
-        let row: Result<Vec<u8>, _> = line
-            .split_whitespace()
-            .map(|s| u8::from_str_radix(s, 16))
-            .collect();
-
-        cells.push(row.map_err(|e| format!("Invalid hex value: {}", e))?);
+        let row = match format {
+            MapFormat::Hex => line
+                .split_whitespace()
+                .map(|s| u8::from_str_radix(s, 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|e| format!("Invalid hex value: {}", e))?,
+            MapFormat::Csv => line
+                .split(',')
+                .map(|s| {
+                    let s = s.trim();
+                    s.parse::<u8>()
+                        .map_err(|_| format!("Invalid CSV value (expected 0-255): {}", s))
+                })
+                .collect::<Result<Vec<u8>, _>>()?,
+        };
+
+        cells.push(row);
     }
 
     if cells.is_empty() {
@@ -122,13 +195,33 @@ fn parse_map(path: &str) -> Result<Grid, String> {
         }
     }
 
-    Ok(Grid::new(cells))
+    let height = cells.len();
+    Ok(Grid::new(width, height, cells.concat()))
+}
+
+// `expected_end` only affects these warnings; the search itself never looks
+// at the value of the goal cell.
+fn endpoint_warnings(grid: &Grid, expected_end: u8) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let start = grid.get(0, 0);
+    if start != 0x00 {
+        warnings.push(format!("start cell (0,0) is 0x{:02X}, expected 00", start));
+    }
+    let (ex, ey) = (grid.width - 1, grid.height - 1);
+    let end = grid.get(ex, ey);
+    if end != expected_end {
+        warnings.push(format!(
+            "end cell ({},{}) is 0x{:02X}, expected {:02X}",
+            ex, ey, end, expected_end
+        ));
+    }
+    warnings
 }
 
 fn save_map(grid: &Grid, path: &str) -> Result<(), String> {
     let mut file = fs::File::create(path).map_err(|e| format!("Failed to create file: {}", e))?;
 
-    for row in &grid.cells {
+    for row in grid.rows() {
         let line: Vec<String> = row.iter().map(|&v| format!("{:02X}", v)).collect();
         writeln!(file, "{}", line.join(" ")).map_err(|e| format!("Failed to write: {}", e))?;
     }
@@ -136,114 +229,474 @@ fn save_map(grid: &Grid, path: &str) -> Result<(), String> {
     Ok(())
 }
 
-fn dijkstra_min(grid: &Grid) -> Option<PathResult> {
-    let mut heap = BinaryHeap::new();
-    let mut dist = vec![vec![u32::MAX; grid.width]; grid.height];
-    let mut parent = vec![vec![None; grid.width]; grid.height];
+fn exit_if_timed_out(outcome: &SearchOutcome, label: &str) {
+    if outcome.timed_out {
+        eprintln!(
+            "Error: {} search timed out after exploring {} nodes",
+            label, outcome.explored
+        );
+        std::process::exit(3);
+    }
+}
 
-    heap.push(Reverse((0u32, 0usize, 0usize)));
-    dist[0][0] = 0;
+fn compute_stats(grid: &Grid, model: &CostModel, goal_value: Option<u8>) -> GridStats {
+    let mut min = u8::MAX;
+    let mut max = u8::MIN;
+    let mut sum = 0u64;
 
-    while let Some(Reverse((cost, x, y))) = heap.pop() {
-        if (x, y) == (grid.width - 1, grid.height - 1) {
-            return Some(reconstruct_path(grid, &parent, &dist, false));
+    for row in grid.rows() {
+        for &value in row {
+            min = min.min(value);
+            max = max.max(value);
+            sum += value as u64;
         }
+    }
 
-        if cost > dist[y][x] {
-            continue;
+    let cell_count = (grid.width * grid.height) as u64;
+    let avg = if cell_count > 0 {
+        sum as f64 / cell_count as f64
+    } else {
+        0.0
+    };
+
+    GridStats {
+        min,
+        max,
+        avg,
+        lower_bound: lower_bound_applies(grid, model, goal_value)
+            .then(|| diagonal_lower_bound(grid)),
+    }
+}
+
+fn print_stats(stats: &GridStats) {
+    println!("\nGRID STATISTICS:");
+    println!("================");
+    println!("Min cell: 0x{:02X} ({})", stats.min, stats.min);
+    println!("Max cell: 0x{:02X} ({})", stats.max, stats.max);
+    println!("Avg cell: {:.2}", stats.avg);
+    match stats.lower_bound {
+        Some(bound) => println!(
+            "Lower bound on path cost: 0x{:X} ({} decimal)",
+            bound, bound
+        ),
+        None => println!("Lower bound on path cost: n/a with these cost options"),
+    }
+}
+
+fn path_to_json(result: &PathResult) -> String {
+    let points: Vec<String> = result
+        .path
+        .iter()
+        .map(|(x, y)| format!("[{},{}]", x, y))
+        .collect();
+    format!(
+        "{{\"cost\":{},\"length\":{},\"path\":[{}]}}",
+        result.total_cost,
+        result.path.len(),
+        points.join(",")
+    )
+}
+
+fn print_json(
+    grid: &Grid,
+    min_path: Option<&PathResult>,
+    max_path: Option<&PathResult>,
+    stats: Option<&GridStats>,
+    meeting_point: Option<(usize, usize)>,
+    explored: Option<(usize, usize)>,
+    diagnostics: Option<&SearchDiagnostics>,
+) {
+    let mut fields = vec![
+        format!("\"width\":{}", grid.width),
+        format!("\"height\":{}", grid.height),
+    ];
+    fields.push(format!(
+        "\"min\":{}",
+        min_path
+            .map(path_to_json)
+            .unwrap_or_else(|| "null".to_string())
+    ));
+    fields.push(format!(
+        "\"max\":{}",
+        max_path
+            .map(path_to_json)
+            .unwrap_or_else(|| "null".to_string())
+    ));
+    if let Some((x, y)) = meeting_point {
+        fields.push(format!("\"meeting_point\":[{},{}]", x, y));
+    }
+    if let Some((min, max)) = explored {
+        fields.push(format!("\"explored\":{{\"min\":{},\"max\":{}}}", min, max));
+    }
+    if let Some(diag) = diagnostics {
+        fields.push(format!(
+            "\"diagnostics\":{{\"max_heap\":{},\"relaxations\":{},\"depth\":{}}}",
+            diag.max_heap, diag.relaxations, diag.depth
+        ));
+    }
+    if let Some(stats) = stats {
+        fields.push(format!(
+            "\"stats\":{{\"min\":{},\"max\":{},\"avg\":{:.2},\"lower_bound\":{}}}",
+            stats.min,
+            stats.max,
+            stats.avg,
+            stats
+                .lower_bound
+                .map_or("null".to_string(), |b| b.to_string())
+        ));
+    }
+    println!("{{{}}}", fields.join(","));
+}
+
+fn verify_min_path(
+    grid: &Grid,
+    model: &CostModel,
+    result: &PathResult,
+    turn_penalty: u32,
+    goal_value: Option<u8>,
+) -> Result<String, String> {
+    let reached_goal = match (goal_value, result.path.last()) {
+        (Some(v), Some(&(x, y))) => grid.get(x, y) == v,
+        (None, Some(&end)) => end == (grid.width - 1, grid.height - 1),
+        (_, None) => false,
+    };
+    if result.path.first() != Some(&(0, 0)) || !reached_goal {
+        return Err("path does not run from (0,0) to the goal".to_string());
+    }
+    for step in result.path.windows(2) {
+        if !grid.neighbors(step[0].0, step[0].1).contains(&step[1]) {
+            return Err(format!("path jumps from {:?} to {:?}", step[0], step[1]));
         }
+    }
 
-        for (nx, ny) in grid.neighbors(x, y) {
-            let new_cost = cost + grid.get(nx, ny) as u32;
-            if new_cost < dist[ny][nx] {
-                dist[ny][nx] = new_cost;
-                parent[ny][nx] = Some((x, y));
-                heap.push(Reverse((new_cost, nx, ny)));
-            }
+    let step_sum: u32 = result
+        .path
+        .windows(2)
+        .map(|w| model.step_cost(grid, w[0], w[1]))
+        .fold(0, add_cost);
+    let recomputed = add_cost(
+        step_sum,
+        (count_turns(&result.path) as u32).saturating_mul(turn_penalty),
+    );
+    if recomputed != result.total_cost {
+        return Err(format!(
+            "reported cost {} but the path sums to {}",
+            result.total_cost, recomputed
+        ));
+    }
+
+    if lower_bound_applies(grid, model, goal_value) {
+        let bound = diagonal_lower_bound(grid);
+        if result.total_cost < bound {
+            return Err(format!(
+                "cost {} is below the lower bound {}",
+                result.total_cost, bound
+            ));
         }
+        return Ok(format!(
+            "cost {} ≥ lower bound {}",
+            result.total_cost, bound
+        ));
     }
+    Ok(format!(
+        "cost {} matches the path (no lower bound applies)",
+        result.total_cost
+    ))
+}
 
-    None
+// diagonal_lower_bound assumes every step costs at least the destination
+// value and that routes cross every anti-diagonal on the way to the corner.
+fn lower_bound_applies(grid: &Grid, model: &CostModel, goal_value: Option<u8>) -> bool {
+    model.mode == CostMode::Cell && model.never_discounts() && goal_value.is_none() && !grid.wrap
 }
 
-fn dijkstra_max(grid: &Grid) -> Option<PathResult> {
-    let mut heap = BinaryHeap::new();
-    let mut dist = vec![vec![0u32; grid.width]; grid.height];
-    let mut parent = vec![vec![None; grid.width]; grid.height];
-    let mut visited = vec![vec![false; grid.width]; grid.height];
+fn print_diagnostics(diag: &SearchDiagnostics) {
+    println!("\nSearch diagnostics:");
+    println!("  {:<16} {:>8}", "Max heap size", diag.max_heap);
+    println!("  {:<16} {:>8}", "Relaxations", diag.relaxations);
+    println!("  {:<16} {:>8}", "Path depth", diag.depth);
+}
 
-    heap.push((0u32, 0usize, 0usize));
-    dist[0][0] = 0;
+fn check_max_cost(min_path: Option<&PathResult>, limit: u32) -> Result<String, String> {
+    match min_path {
+        Some(min) if min.total_cost <= limit => Ok(format!(
+            "PASS: minimum cost {} is within --max-cost {}",
+            min.total_cost, limit
+        )),
+        Some(min) => Err(format!(
+            "FAIL: minimum cost {} exceeds --max-cost {}",
+            min.total_cost, limit
+        )),
+        None => Err("FAIL: no path found".to_string()),
+    }
+}
 
-    while let Some((cost, x, y)) = heap.pop() {
-        if visited[y][x] {
-            continue;
-        }
-        visited[y][x] = true;
+fn enforce_max_cost(min_path: Option<&PathResult>, limit: u32, to_stderr: bool) {
+    let verdict = check_max_cost(min_path, limit);
+    let message = match &verdict {
+        Ok(m) | Err(m) => m,
+    };
+    if to_stderr {
+        eprintln!("{}", message);
+    } else {
+        println!("\n{}", message);
+    }
+    if verdict.is_err() {
+        std::process::exit(1);
+    }
+}
+
+const COLOR_BUCKETS: [(u8, u8, &str, &str); 8] = [
+    (0x00, 0x1F, "\x1b[38;5;196m", "red"),
+    (0x20, 0x3F, "\x1b[38;5;208m", "orange"),
+    (0x40, 0x5F, "\x1b[38;5;226m", "yellow"),
+    (0x60, 0x7F, "\x1b[38;5;46m", "green"),
+    (0x80, 0x9F, "\x1b[38;5;51m", "cyan"),
+    (0xA0, 0xBF, "\x1b[38;5;21m", "blue"),
+    (0xC0, 0xDF, "\x1b[38;5;129m", "purple"),
+    (0xE0, 0xFF, "\x1b[38;5;201m", "magenta"),
+];
+
+// Compact rendering shades each COLOR_BUCKETS range with one character so a
+// cell takes a single column instead of three.
+const BUCKET_CHARS: [char; 8] = ['.', ':', '-', '=', '+', '*', '#', '%'];
+
+fn bucket_char(value: u8) -> char {
+    BUCKET_CHARS[value as usize * BUCKET_CHARS.len() / 256]
+}
+
+fn get_color(value: u8) -> &'static str {
+    COLOR_BUCKETS
+        .iter()
+        .find(|(low, high, _, _)| (*low..=*high).contains(&value))
+        .map(|(_, _, color, _)| *color)
+        .unwrap_or("")
+}
 
-        if (x, y) == (grid.width - 1, grid.height - 1) {
-            return Some(reconstruct_path(grid, &parent, &dist, true));
+fn print_legend(color: bool, cell_char: Option<char>) {
+    println!("\nLEGEND:");
+    if let Some(path_char) = cell_char {
+        for (low, high, code, name) in COLOR_BUCKETS.iter() {
+            let c = bucket_char(*low);
+            if color {
+                println!("  {}{}\x1b[0m {:02X}-{:02X}  {}", code, c, low, high, name);
+            } else {
+                println!("  {} {:02X}-{:02X}  {}", c, low, high, name);
+            }
+        }
+        println!("  {}  path", path_char);
+        return;
+    }
+    for (low, high, code, name) in COLOR_BUCKETS.iter() {
+        if color {
+            println!("  {}██\x1b[0m {:02X}-{:02X}  {}", code, low, high, name);
+        } else {
+            println!("  {:02X}-{:02X}  {}", low, high, name);
         }
+    }
+}
 
-        for (nx, ny) in grid.neighbors(x, y) {
-            if !visited[ny][nx] {
-                let new_cost = cost + grid.get(nx, ny) as u32;
-                if new_cost > dist[ny][nx] {
-                    dist[ny][nx] = new_cost;
-                    parent[ny][nx] = Some((x, y));
-                    heap.push((new_cost, nx, ny));
+fn print_rainbow_grid(grid: &Grid, color: bool, cell_char: Option<char>) {
+    println!("\nHEXADECIMAL GRID (rainbow gradient):");
+    println!("═══════════════════════════════════════════════════════════════════════════════");
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let value = grid.get(x, y);
+            if cell_char.is_some() {
+                if color {
+                    print!("{}{}\x1b[0m", get_color(value), bucket_char(value));
+                } else {
+                    print!("{}", bucket_char(value));
                 }
+            } else if color {
+                print!("{}{:02X}\x1b[0m ", get_color(value), value);
+            } else {
+                print!("{:02X} ", value);
             }
         }
+        println!();
     }
-
-    None
 }
 
-fn reconstruct_path(
-    grid: &Grid,
-    parent: &[Vec<Option<(usize, usize)>>],
-    dist: &[Vec<u32>],
-    _is_max: bool,
-) -> PathResult {
-    let mut path = Vec::new();
-    let mut current = (grid.width - 1, grid.height - 1);
+fn print_heatmap(grid: &Grid, settle_order: &[u32], color: bool) {
+    let settled = settle_order
+        .iter()
+        .filter(|&&r| r != u32::MAX)
+        .count()
+        .max(1);
+    println!("\nSEARCH HEATMAP (settle order, early → late):");
+    println!("═══════════════════════════════════════════════════════════════════════════════");
+    for (row, ranks) in grid.rows().zip(settle_order.chunks(grid.width)) {
+        for (&value, &rank) in row.iter().zip(ranks) {
+            if rank == u32::MAX {
+                print!("·· ");
+                continue;
+            }
+            let bucket = rank as usize * COLOR_BUCKETS.len() / settled;
+            if color {
+                print!("{}{:02X}\x1b[0m ", COLOR_BUCKETS[bucket].2, value);
+            } else {
+                print!("{:>2} ", bucket);
+            }
+        }
+        println!();
+    }
+    println!("Settled {} of {} cells", settled, grid.width * grid.height);
+}
 
-    while let Some((x, y)) = Some(current) {
-        path.push((x, y));
+// Without a highlight color, path cells are told apart by the --cell-char
+// character, or by a `*` after the value in the hex view.
+fn print_path_heading(title: &str, color_name: &str, color: bool, cell_char: Option<char>) {
+    let how = if color {
+        format!("shown in {}", color_name)
+    } else {
+        format!("marked with {}", cell_char.unwrap_or('*'))
+    };
+    let heading = format!("{} ({}):", title, how);
+    println!("{}", heading);
+    println!("{}", "═".repeat(heading.chars().count()));
+}
 
-        if (x, y) == (0, 0) {
-            break;
+fn print_highlighted(
+    grid: &Grid,
+    cells: &HashSet<Point>,
+    highlight: Option<&str>,
+    cell_char: Option<char>,
+) {
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let value = grid.get(x, y);
+            let on_path = cells.contains(&(x, y));
+            match (cell_char, highlight) {
+                (Some(path_char), Some(highlight)) if on_path => {
+                    print!("{}{}\x1b[0m", highlight, path_char)
+                }
+                (Some(_), Some(_)) => {
+                    print!("{}{}\x1b[0m", get_color(value), bucket_char(value))
+                }
+                (Some(path_char), None) if on_path => print!("{}", path_char),
+                (Some(_), None) => print!("{}", bucket_char(value)),
+                (None, Some(highlight)) if on_path => {
+                    print!("{}{:02X}\x1b[0m ", highlight, value)
+                }
+                (None, Some(_)) => print!("{}{:02X}\x1b[0m ", get_color(value), value),
+                (None, None) if on_path => print!("{:02X}*", value),
+                (None, None) => print!("{:02X} ", value),
+            }
         }
+        println!();
+    }
+}
 
-        if let Some(p) = parent[y][x] {
-            current = p;
-        } else {
-            break;
+fn load_path(path: &str, grid: &Grid) -> Result<Vec<Point>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let mut points = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
         }
+        let parsed = line
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+        let (x, y): Point = parsed
+            .ok_or_else(|| format!("Line {}: expected x,y but found '{}'", line_no + 1, line))?;
+        if x >= grid.width || y >= grid.height {
+            return Err(format!(
+                "Line {}: ({},{}) is outside the {}×{} grid",
+                line_no + 1,
+                x,
+                y,
+                grid.width,
+                grid.height
+            ));
+        }
+        points.push((x, y));
     }
+    if points.is_empty() {
+        return Err(format!("{} contains no coordinates", path));
+    }
+    Ok(points)
+}
 
-    path.reverse();
-
-    let total_cost = dist[grid.height - 1][grid.width - 1];
+fn parse_waypoints(s: &str) -> Option<Vec<Point>> {
+    s.split(';')
+        .map(|pair| {
+            let (x, y) = pair.split_once(',')?;
+            Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+        })
+        .collect()
+}
 
-    PathResult { path, total_cost }
+// Chains minimum-cost segments from the start through each waypoint in
+// order and on to the goal, so the combined cost is the sum of the legs.
+fn route_through_waypoints(
+    grid: &Grid,
+    model: &CostModel,
+    waypoints: &[Point],
+    deadline: Option<Instant>,
+) -> Result<PathResult, String> {
+    let mut stops = vec![(0, 0)];
+    stops.extend_from_slice(waypoints);
+    stops.push((grid.width - 1, grid.height - 1));
+
+    let mut combined = PathResult {
+        path: vec![(0, 0)],
+        total_cost: 0,
+    };
+    for leg in stops.windows(2) {
+        let outcome = dijkstra_between(grid, model, leg[0], leg[1], deadline);
+        exit_if_timed_out(&outcome, "waypoint");
+        let segment = outcome.result.ok_or_else(|| {
+            format!(
+                "No route from ({},{}) to ({},{})",
+                leg[0].0, leg[0].1, leg[1].0, leg[1].1
+            )
+        })?;
+        combined.path.extend_from_slice(&segment.path[1..]);
+        combined.total_cost = add_cost(combined.total_cost, segment.total_cost);
+    }
+    Ok(combined)
 }
 
-fn get_color(value: u8) -> &'static str {
-    match value {
-        0x00..=0x1F => "\x1b[38;5;196m",
-        0x20..=0x3F => "\x1b[38;5;208m",
-        0x40..=0x5F => "\x1b[38;5;226m",
-        0x60..=0x7F => "\x1b[38;5;46m",
-        0x80..=0x9F => "\x1b[38;5;51m",
-        0xA0..=0xBF => "\x1b[38;5;21m",
-        0xC0..=0xDF => "\x1b[38;5;129m",
-        0xE0..=0xFF => "\x1b[38;5;201m",
+fn show_imported_path(
+    grid: &Grid,
+    model: &CostModel,
+    points: &[Point],
+    cell_char: Option<char>,
+    color: bool,
+) {
+    for pair in points.windows(2) {
+        if !grid.neighbors(pair[0].0, pair[0].1).contains(&pair[1]) {
+            eprintln!(
+                "Warning: ({},{}) → ({},{}) is not a move between adjacent cells",
+                pair[0].0, pair[0].1, pair[1].0, pair[1].1
+            );
+        }
     }
+
+    let total_cost: u32 = points
+        .windows(2)
+        .map(|pair| model.step_cost(grid, pair[0], pair[1]))
+        .fold(0, add_cost);
+    let cells: HashSet<Point> = points.iter().cloned().collect();
+
+    print_path_heading("IMPORTED PATH", "WHITE", color, cell_char);
+    print_highlighted(grid, &cells, color.then_some("\x1b[47m\x1b[30m"), cell_char);
+    println!(
+        "\nCost: {} over {} steps (imported)",
+        total_cost,
+        points.len() - 1
+    );
 }
 
-fn visualize_grid(grid: &Grid, min_path: Option<&PathResult>, max_path: Option<&PathResult>) {
+fn visualize_grid(
+    grid: &Grid,
+    min_path: Option<&PathResult>,
+    max_path: Option<&PathResult>,
+    cell_char: Option<char>,
+    color: bool,
+) {
     let min_set: HashSet<_> = min_path
         .map(|p| p.path.iter().cloned().collect())
         .unwrap_or_default();
@@ -251,59 +704,88 @@ fn visualize_grid(grid: &Grid, min_path: Option<&PathResult>, max_path: Option<&
         .map(|p| p.path.iter().cloned().collect())
         .unwrap_or_default();
 
-    println!("\nHEXADECIMAL GRID (rainbow gradient):");
-    println!("═══════════════════════════════════════════════════════════════════════════════");
-    for y in 0..grid.height {
-        for x in 0..grid.width {
-            let value = grid.get(x, y);
-            let color = get_color(value);
-            print!("{}{:02X}\x1b[0m ", color, value);
-        }
-        println!();
-    }
+    print_rainbow_grid(grid, color, cell_char);
 
     if min_path.is_some() {
-        println!("\nMINIMUM COST PATH (shown in WHITE):");
-        println!("═══════════════════════════════════");
-        for y in 0..grid.height {
-            for x in 0..grid.width {
-                let value = grid.get(x, y);
-                if min_set.contains(&(x, y)) {
-                    print!("\x1b[47m\x1b[30m{:02X}\x1b[0m ", value);
-                } else {
-                    let color = get_color(value);
-                    print!("{}{:02X}\x1b[0m ", color, value);
-                }
-            }
-            println!();
-        }
+        println!();
+        print_path_heading("MINIMUM COST PATH", "WHITE", color, cell_char);
+        print_highlighted(
+            grid,
+            &min_set,
+            color.then_some("\x1b[47m\x1b[30m"),
+            cell_char,
+        );
         if let Some(min) = min_path {
             println!("\nCost: {} (minimum)", min.total_cost);
         }
     }
 
     if max_path.is_some() {
-        println!("\nMAXIMUM COST PATH (shown in RED):");
-        println!("═════════════════════════════════");
-        for y in 0..grid.height {
-            for x in 0..grid.width {
-                let value = grid.get(x, y);
-                if max_set.contains(&(x, y)) {
-                    print!("\x1b[41m\x1b[37m{:02X}\x1b[0m ", value);
-                } else {
-                    let color = get_color(value);
-                    print!("{}{:02X}\x1b[0m ", color, value);
-                }
-            }
-            println!();
-        }
+        println!();
+        print_path_heading("MAXIMUM COST PATH", "RED", color, cell_char);
+        print_highlighted(
+            grid,
+            &max_set,
+            color.then_some("\x1b[41m\x1b[37m"),
+            cell_char,
+        );
         if let Some(max) = max_path {
             println!("\nCost: {} (maximum)", max.total_cost);
         }
     }
 }
 
-fn print_path_analysis(grid: &Grid, result: &PathResult, label: &str) {
+fn print_comparison(min: &PathResult, max: &PathResult) {
+    let min_set: HashSet<_> = min.path.iter().cloned().collect();
+    let max_set: HashSet<_> = max.path.iter().cloned().collect();
+    let shared = min_set.intersection(&max_set).count();
+
+    println!("\nPATH COMPARISON:");
+    println!("================");
+    println!(
+        "Cost difference: {} (max {} - min {})",
+        max.total_cost as i64 - min.total_cost as i64,
+        max.total_cost,
+        min.total_cost
+    );
+    println!(
+        "Shared cells: {} of {} (min) / {} (max)",
+        shared,
+        min.path.len(),
+        max.path.len()
+    );
+    println!(
+        "Length difference: {} steps",
+        max.path.len() as i64 - min.path.len() as i64
+    );
+}
+
+fn print_path_only(grid: &Grid, result: &PathResult, color: bool) {
+    let mut arrows = vec!["·"; grid.width * grid.height];
+    for pair in result.path.windows(2) {
+        let ((x, y), (nx, ny)) = (pair[0], pair[1]);
+        arrows[grid.index(x, y)] = ["→", "←", "↓", "↑"][move_direction((x, y), (nx, ny))];
+    }
+    if let Some(&(x, y)) = result.path.last() {
+        arrows[grid.index(x, y)] = "●";
+    }
+
+    for row in arrows.chunks(grid.width) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|&c| {
+                if color && c != "·" {
+                    format!("\x1b[1;32m{}\x1b[0m", c)
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect();
+        println!("{}", cells.join(" "));
+    }
+}
+
+fn print_path_analysis(grid: &Grid, model: &CostModel, result: &PathResult, label: &str) {
     println!("\n{} COST PATH:", label);
     println!("==================");
     println!(
@@ -325,58 +807,171 @@ fn print_path_analysis(grid: &Grid, result: &PathResult, label: &str) {
         if i == 0 {
             println!("  Start  0x{:02X} ({},{})", value, x, y);
         } else {
-            println!("    →    0x{:02X} ({},{})  +{}", value, x, y, value);
+            let step = model.step_cost(grid, result.path[i - 1], (x, y));
+            println!("    →    0x{:02X} ({},{})  +{}", value, x, y, step);
         }
     }
     println!("  Total: 0x{:X} ({})", result.total_cost, result.total_cost);
 }
 
-fn animate_pathfinding(grid: &Grid) {
-    println!("Searching for minimum cost path...\n");
+// Ctrl-C pauses the minimum search and saves its state to `save_to`; once
+// that search is over, Ctrl-C exits as usual.
+fn run_checkpointed(
+    grid: &Grid,
+    model: &CostModel,
+    resume: Option<&str>,
+    save_to: &str,
+    deadline: Option<Instant>,
+) -> SearchOutcome {
+    let state = match resume {
+        Some(path) => {
+            let bytes = fs::read(path).unwrap_or_else(|e| {
+                eprintln!("Error: Cannot read {}: {}", path, e);
+                std::process::exit(1);
+            });
+            let state = SearchState::from_bytes(&bytes, grid, model).unwrap_or_else(|e| {
+                eprintln!("Error: {}: {}", path, e);
+                std::process::exit(1);
+            });
+            eprintln!(
+                "Resuming from {} ({} nodes already explored)",
+                path, state.explored
+            );
+            state
+        }
+        None => SearchState::start(grid),
+    };
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let searching = Arc::new(AtomicBool::new(true));
+    let (flag, active) = (Arc::clone(&interrupted), Arc::clone(&searching));
+    if let Err(e) = ctrlc::set_handler(move || {
+        if active.load(Ordering::SeqCst) {
+            flag.store(true, Ordering::SeqCst);
+        } else {
+            std::process::exit(130);
+        }
+    }) {
+        eprintln!("Warning: cannot install the Ctrl-C handler: {}", e);
+    }
+
+    let outcome = dijkstra_min_checkpointed(grid, model, state, &interrupted, deadline);
+    searching.store(false, Ordering::SeqCst);
+    match outcome {
+        Checkpointed::Finished(outcome) => outcome,
+        Checkpointed::Interrupted(state) => {
+            if let Err(e) = fs::write(save_to, state.to_bytes(grid, model)) {
+                eprintln!("Error: Cannot write {}: {}", save_to, e);
+                std::process::exit(1);
+            }
+            eprintln!(
+                "Interrupted after {} nodes, search state saved to {} (continue with --resume {})",
+                state.explored, save_to, save_to
+            );
+            std::process::exit(130);
+        }
+    }
+}
+
+fn run_benchmark(grid: &Grid, model: &CostModel, deadline: Option<Instant>) {
+    type Search = fn(&Grid, &CostModel, Option<Instant>) -> SearchOutcome;
+    let searches: [(&str, Search); 3] = [
+        ("dijkstra", |g, m, d| dijkstra_min(g, m, None, d)),
+        ("a*", astar_min),
+        ("bidirectional", |g, m, d| dijkstra_bidirectional(g, m, d).0),
+    ];
+
+    let mut rows = Vec::new();
+    for (name, search) in searches {
+        let started = Instant::now();
+        let outcome = search(grid, model, deadline);
+        let elapsed = started.elapsed();
+        exit_if_timed_out(&outcome, name);
+        let cost = outcome.result.map(|r| r.total_cost);
+        rows.push((name, cost, outcome.explored, elapsed));
+    }
+
+    println!("ALGORITHM COMPARISON:");
+    println!("=====================");
+    println!(
+        "{:<14} {:>10} {:>10} {:>12}",
+        "Algorithm", "Cost", "Explored", "Time"
+    );
+    for (name, cost, explored, elapsed) in &rows {
+        let cost = cost.map_or("-".to_string(), |c| c.to_string());
+        println!(
+            "{:<14} {:>10} {:>10} {:>12.3?}",
+            name, cost, explored, elapsed
+        );
+    }
+
+    if rows.iter().all(|row| row.1 == rows[0].1) {
+        println!("\n✓ All algorithms agree on the minimum cost");
+    } else {
+        eprintln!("Error: algorithms disagree on the minimum cost");
+        std::process::exit(1);
+    }
+}
+
+fn animate_pathfinding(grid: &Grid, model: &CostModel, events: bool) {
+    if !events {
+        println!("Searching for minimum cost path...\n");
+    }
 
     let mut heap = BinaryHeap::new();
-    let mut dist = vec![vec![u32::MAX; grid.width]; grid.height];
-    let mut visited = vec![vec![false; grid.width]; grid.height];
+    let mut dist = vec![u32::MAX; grid.width * grid.height];
+    let mut visited = vec![false; grid.width * grid.height];
 
     heap.push(Reverse((0u32, 0usize, 0usize)));
-    dist[0][0] = 0;
+    dist[grid.index(0, 0)] = 0;
 
     let mut step = 0;
 
     while let Some(Reverse((cost, x, y))) = heap.pop() {
-        if visited[y][x] {
+        if visited[grid.index(x, y)] {
             continue;
         }
-        visited[y][x] = true;
+        visited[grid.index(x, y)] = true;
         step += 1;
 
-        println!("Step {}: Exploring ({},{}) - cost: {}", step, x, y, cost);
-
-        for (row_y, row) in visited.iter().enumerate() {
-            for (col_x, &is_visited) in row.iter().enumerate() {
-                if is_visited {
-                    print!("[✓]");
-                } else if (col_x, row_y) == (x, y) {
-                    print!("[*]");
-                } else {
-                    print!("[ ]");
+        if events {
+            println!(
+                "{{\"step\":{},\"x\":{},\"y\":{},\"cost\":{}}}",
+                step, x, y, cost
+            );
+            std::io::stdout().flush().unwrap();
+            if (x, y) == (grid.width - 1, grid.height - 1) {
+                break;
+            }
+        } else {
+            println!("Step {}: Exploring ({},{}) - cost: {}", step, x, y, cost);
+
+            for (row_y, row) in visited.chunks(grid.width).enumerate() {
+                for (col_x, &is_visited) in row.iter().enumerate() {
+                    if is_visited {
+                        print!("[✓]");
+                    } else if (col_x, row_y) == (x, y) {
+                        print!("[*]");
+                    } else {
+                        print!("[ ]");
+                    }
                 }
+                println!();
             }
             println!();
-        }
-        println!();
-        thread::sleep(Duration::from_millis(200));
+            thread::sleep(Duration::from_millis(200));
 
-        if (x, y) == (grid.width - 1, grid.height - 1) {
-            println!("✓ Reached destination!");
-            break;
+            if (x, y) == (grid.width - 1, grid.height - 1) {
+                println!("✓ Reached destination!");
+                break;
+            }
         }
 
         for (nx, ny) in grid.neighbors(x, y) {
-            if !visited[ny][nx] {
-                let new_cost = cost + grid.get(nx, ny) as u32;
-                if new_cost < dist[ny][nx] {
-                    dist[ny][nx] = new_cost;
+            if !visited[grid.index(nx, ny)] {
+                let new_cost = add_cost(cost, model.step_cost(grid, (x, y), (nx, ny)));
+                if new_cost < dist[grid.index(nx, ny)] {
+                    dist[grid.index(nx, ny)] = new_cost;
                     heap.push(Reverse((new_cost, nx, ny)));
                 }
             }
@@ -392,7 +987,7 @@ fn print_help() {
     println!("Map format:");
     println!("  - Each cell: 00-FF (hexadecimal)");
     println!("  - Start: top-left (must be 00)");
-    println!("  - End: bottom-right (must be FF)");
+    println!("  - End: bottom-right (expected FF, see --goal-is)");
     println!("  - Moves: up, down, left, right");
     println!();
     println!("Usage: hexpath [OPTIONS] [map]");
@@ -401,25 +996,141 @@ fn print_help() {
     println!("  [map]  Map file (hex values, space separated)");
     println!();
     println!("Options:");
-    println!("      --generate <widthxheight>  Generate random map (e.g., 8x4, 10x10)");
+    println!("      --generate <widthxheight>  Generate a random map and analyze it (e.g., 8x4)");
+    println!("      --format <hex|csv>         Map file format [default: from extension]");
     println!("      --output <file>            Save generated map to file");
+    println!("      --min-value <n>            Lowest random interior value (decimal or 0x hex) [default: 0x01]");
+    println!("      --max-value <n>            Highest random interior value (decimal or 0x hex) [default: 0xFE]");
+    println!("      --corridor                 Carve a low-cost path into the generated map");
+    println!(
+        "      --ensure-solvable          Regenerate until the goal is reachable from the start"
+    );
+    println!("      --retries <n>              Attempt cap for --ensure-solvable [default: 100]");
+    println!("      --relax                    Don't warn when start/end aren't 00/FF");
+    println!(
+        "      --goal-is <hex>            End value the start/end warnings expect [default: FF]"
+    );
+    println!("      --preview                  Show a colored view of the generated map");
+    println!("      --legend                   Show the value ranges behind each color");
     println!("      --visualize                Show colored map");
+    println!("      --path-only                Show only the minimum path as arrows");
+    println!("      --no-color                 Disable ANSI colors in map, path and legend output");
+    println!("      --cell-char <c>            Draw one character per cell by value range, with c on paths");
     println!("      --both                     Show both min and max paths");
+    println!("      --compare                  Summarize how the min and max paths differ");
+    println!(
+        "      --reachable                Only check whether the goal can be reached (exit 0/1)"
+    );
     println!("      --animate                  Animate pathfinding");
-    println!("  -h, --help                     Print help");
-}
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    let mut generate_spec: Option<String> = None;
-    let mut output_path: Option<String> = None;
-    let mut map_path: Option<String> = None;
-    let mut visualize = false;
-    let mut animate = false;
-
-    let mut i = 1;
-    while i < args.len() {
+    println!("      --events                   Stream each explored node as a JSON line instead");
+    println!("      --bidirectional            Search for the minimum path from both ends");
+    println!(
+        "      --stats                    Show min/max/avg cell values and a cost lower bound"
+    );
+    println!("      --json                     Print the analysis as JSON");
+    println!(
+        "      --cost-only                Print only the minimum cost (and maximum with --both)"
+    );
+    println!(
+        "      --time-limit <ms>          Abort searches that run longer than ms (exit code 3)"
+    );
+    println!(
+        "      --bench                    Time Dijkstra, A* and bidirectional search on the map"
+    );
+    println!("      --wrap                     Toroidal grid: moving off an edge enters the opposite edge");
+    println!("                                 (changes optimal paths; disables the --verify lower bound)");
+    println!(
+        "      --count-optimal            Report how many distinct paths reach the minimum cost"
+    );
+    println!("      --count-explored           Report how many nodes each search visited");
+    println!(
+        "      --heatmap                  Color cells by the order the minimum search settled them"
+    );
+    println!(
+        "      --show-path <file>         Highlight and cost a route of x,y lines from a file"
+    );
+    println!(
+        "      --waypoints <x,y;...>      Find the minimum path visiting these cells in order"
+    );
+    println!("      --goal-value <hex>         Stop at the cheapest cell holding this value");
+    println!(
+        "      --verify                   Sanity-check the minimum path against a lower bound"
+    );
+    println!("      --diagnostics              Report heap size, relaxations and path depth of the search");
+    println!("      --checkpoint <file>        On Ctrl-C, save the minimum search state to file and exit");
+    println!("      --resume <file>            Continue a saved minimum search (same map and --cost options)");
+    println!("                                 (about 8 bytes per cell; grids up to 2^32 cells)");
+    println!("      --prune-below <n>          Find the exact maximum path, skipping routes that can't reach n");
+    println!(
+        "                                 (runtime only: same result while n ≤ the true maximum)"
+    );
+    println!("      --max-cost <n>             Exit with status 1 if the minimum cost exceeds n");
+    println!("      --cost <cell|gradient>     Step cost: destination value or value difference [default: cell]");
+    println!("      --turn-penalty <n>         Add n to the cost of every change of direction [default: 0]");
+    println!("                                 (tracks the arrival direction, so the search uses ~4x memory)");
+    println!("      --uphill-penalty <f>       Multiply the cost of moving to a higher cell, 0-1000 [default: 1.0]");
+    println!(
+        "      --cost-east <f>            Multiply the cost of moving right, 0-1000 [default: 1.0]"
+    );
+    println!(
+        "      --cost-west <f>            Multiply the cost of moving left, 0-1000 [default: 1.0]"
+    );
+    println!(
+        "      --cost-south <f>           Multiply the cost of moving down, 0-1000 [default: 1.0]"
+    );
+    println!(
+        "      --cost-north <f>           Multiply the cost of moving up, 0-1000 [default: 1.0]"
+    );
+    println!("  -h, --help                     Print help");
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut generate_spec: Option<String> = None;
+    let mut output_path: Option<String> = None;
+    let mut map_path: Option<String> = None;
+    let mut map_format: Option<MapFormat> = None;
+    let mut visualize = false;
+    let mut preview = false;
+    let mut legend = false;
+    let mut path_only = false;
+    let mut no_color = false;
+    let mut cell_char: Option<char> = None;
+    let mut relax = false;
+    let mut expected_end: u8 = 0xFF;
+    let mut gen_options = GenOptions::default();
+    let mut animate = false;
+    let mut events = false;
+    let mut compare = false;
+    let mut reachable = false;
+    let mut bidirectional = false;
+    let mut show_stats = false;
+    let mut json = false;
+    let mut both = false;
+    let mut cost_only = false;
+    let mut count_explored = false;
+    let mut bench = false;
+    let mut count_optimal = false;
+    let mut wrap = false;
+    let mut diagnostics = false;
+    let mut heatmap = false;
+    let mut verify = false;
+    let mut goal_value: Option<u8> = None;
+    let mut show_path: Option<String> = None;
+    let mut waypoints: Option<Vec<Point>> = None;
+    let mut time_limit: Option<u64> = None;
+    let mut turn_penalty: u32 = 0;
+    let mut max_cost: Option<u32> = None;
+    let mut prune_below: Option<u32> = None;
+    let mut checkpoint_path: Option<String> = None;
+    let mut resume_path: Option<String> = None;
+    let mut model = CostModel::default();
+    let mut ensure_solvable = false;
+    let mut retries: usize = 100;
+
+    let mut i = 1;
+    while i < args.len() {
         match args[i].as_str() {
             "-h" | "--help" => {
                 print_help();
@@ -434,6 +1145,18 @@ fn main() {
                     std::process::exit(1);
                 }
             }
+            "--format" => {
+                if i + 1 < args.len() {
+                    map_format = Some(MapFormat::parse(&args[i + 1]).unwrap_or_else(|| {
+                        eprintln!("Error: --format expects 'hex' or 'csv'");
+                        std::process::exit(1);
+                    }));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --format requires a value");
+                    std::process::exit(1);
+                }
+            }
             "--output" => {
                 if i + 1 < args.len() {
                     output_path = Some(args[i + 1].clone());
@@ -443,17 +1166,317 @@ fn main() {
                     std::process::exit(1);
                 }
             }
+            "--min-value" | "--max-value" => {
+                if i + 1 < args.len() {
+                    let value = parse_cell_value(&args[i + 1]).unwrap_or_else(|| {
+                        eprintln!("Error: {} expects a value from 0x01 to 0xFE", args[i]);
+                        std::process::exit(1);
+                    });
+                    if args[i] == "--min-value" {
+                        gen_options.min_value = value;
+                    } else {
+                        gen_options.max_value = value;
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("Error: {} requires a value", args[i]);
+                    std::process::exit(1);
+                }
+            }
+            "--corridor" => {
+                gen_options.corridor = true;
+                i += 1;
+            }
+            "--ensure-solvable" => {
+                ensure_solvable = true;
+                i += 1;
+            }
+            "--retries" => {
+                if i + 1 < args.len() {
+                    retries = match args[i + 1].parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => {
+                            eprintln!("Error: --retries expects a positive integer");
+                            std::process::exit(1);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    eprintln!("Error: --retries requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--checkpoint" | "--resume" => {
+                if i + 1 < args.len() {
+                    if args[i] == "--checkpoint" {
+                        checkpoint_path = Some(args[i + 1].clone());
+                    } else {
+                        resume_path = Some(args[i + 1].clone());
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("Error: {} requires a file", args[i]);
+                    std::process::exit(1);
+                }
+            }
+            "--prune-below" => {
+                if i + 1 < args.len() {
+                    prune_below = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --prune-below expects a non-negative integer");
+                        std::process::exit(1);
+                    }));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --prune-below requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--max-cost" => {
+                if i + 1 < args.len() {
+                    max_cost = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --max-cost expects a non-negative integer");
+                        std::process::exit(1);
+                    }));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --max-cost requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--cost" => {
+                if i + 1 < args.len() {
+                    model.mode = CostMode::parse(&args[i + 1]).unwrap_or_else(|| {
+                        eprintln!("Error: --cost expects 'cell' or 'gradient'");
+                        std::process::exit(1);
+                    });
+                    i += 2;
+                } else {
+                    eprintln!("Error: --cost requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--uphill-penalty" => {
+                if i + 1 < args.len() {
+                    model.uphill_penalty = match args[i + 1].parse::<f64>() {
+                        Ok(f) if (0.0..=MAX_COST_FACTOR).contains(&f) => f,
+                        _ => {
+                            eprintln!(
+                                "Error: --uphill-penalty expects a number from 0 to {}",
+                                MAX_COST_FACTOR
+                            );
+                            std::process::exit(1);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    eprintln!("Error: --uphill-penalty requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--cost-east" | "--cost-west" | "--cost-south" | "--cost-north" => {
+                if i + 1 < args.len() {
+                    let dir = match args[i].as_str() {
+                        "--cost-east" => 0,
+                        "--cost-west" => 1,
+                        "--cost-south" => 2,
+                        _ => 3,
+                    };
+                    model.direction_cost[dir] = match args[i + 1].parse::<f64>() {
+                        Ok(f) if (0.0..=MAX_COST_FACTOR).contains(&f) => f,
+                        _ => {
+                            eprintln!(
+                                "Error: {} expects a number from 0 to {}",
+                                args[i], MAX_COST_FACTOR
+                            );
+                            std::process::exit(1);
+                        }
+                    };
+                    i += 2;
+                } else {
+                    eprintln!("Error: {} requires a value", args[i]);
+                    std::process::exit(1);
+                }
+            }
+            "--relax" => {
+                relax = true;
+                i += 1;
+            }
+            "--preview" => {
+                preview = true;
+                i += 1;
+            }
+            "--legend" => {
+                legend = true;
+                i += 1;
+            }
             "--visualize" => {
                 visualize = true;
                 i += 1;
             }
+            "--path-only" => {
+                path_only = true;
+                i += 1;
+            }
+            "--no-color" => {
+                no_color = true;
+                i += 1;
+            }
+            "--cell-char" => {
+                if i + 1 < args.len() {
+                    let mut chars = args[i + 1].chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if !BUCKET_CHARS.contains(&c) => cell_char = Some(c),
+                        _ => {
+                            eprintln!(
+                                "Error: --cell-char takes one character other than {}",
+                                BUCKET_CHARS.iter().collect::<String>()
+                            );
+                            std::process::exit(1);
+                        }
+                    }
+                    i += 2;
+                } else {
+                    eprintln!("Error: --cell-char requires a value");
+                    std::process::exit(1);
+                }
+            }
             "--both" => {
+                both = true;
+                i += 1;
+            }
+            "--cost-only" => {
+                cost_only = true;
+                i += 1;
+            }
+            "--compare" => {
+                compare = true;
+                i += 1;
+            }
+            "--reachable" => {
+                reachable = true;
+                i += 1;
+            }
+            "--events" => {
+                events = true;
                 i += 1;
             }
             "--animate" => {
                 animate = true;
                 i += 1;
             }
+            "--bidirectional" => {
+                bidirectional = true;
+                i += 1;
+            }
+            "--stats" => {
+                show_stats = true;
+                i += 1;
+            }
+            "--json" => {
+                json = true;
+                i += 1;
+            }
+            "--turn-penalty" => {
+                if i + 1 < args.len() {
+                    turn_penalty = args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --turn-penalty expects a non-negative integer");
+                        std::process::exit(1);
+                    });
+                    i += 2;
+                } else {
+                    eprintln!("Error: --turn-penalty requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--time-limit" => {
+                if i + 1 < args.len() {
+                    time_limit = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --time-limit expects milliseconds");
+                        std::process::exit(1);
+                    }));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --time-limit requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--show-path" => {
+                if i + 1 < args.len() {
+                    show_path = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --show-path requires a file");
+                    std::process::exit(1);
+                }
+            }
+            "--waypoints" => {
+                if i + 1 < args.len() {
+                    waypoints = Some(parse_waypoints(&args[i + 1]).unwrap_or_else(|| {
+                        eprintln!("Error: --waypoints expects x,y pairs separated by ';'");
+                        std::process::exit(1);
+                    }));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --waypoints requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--goal-is" => {
+                if i + 1 < args.len() {
+                    let value = &args[i + 1];
+                    let digits = value.strip_prefix("0x").unwrap_or(value);
+                    expected_end = u8::from_str_radix(digits, 16).unwrap_or_else(|_| {
+                        eprintln!("Error: --goal-is expects a hex byte such as FF");
+                        std::process::exit(1);
+                    });
+                    i += 2;
+                } else {
+                    eprintln!("Error: --goal-is requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--goal-value" => {
+                if i + 1 < args.len() {
+                    let value = &args[i + 1];
+                    let digits = value.strip_prefix("0x").unwrap_or(value);
+                    goal_value = Some(u8::from_str_radix(digits, 16).unwrap_or_else(|_| {
+                        eprintln!("Error: --goal-value expects a hex byte such as FF");
+                        std::process::exit(1);
+                    }));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --goal-value requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "--verify" => {
+                verify = true;
+                i += 1;
+            }
+            "--heatmap" => {
+                heatmap = true;
+                i += 1;
+            }
+            "--diagnostics" => {
+                diagnostics = true;
+                i += 1;
+            }
+            "--wrap" => {
+                wrap = true;
+                i += 1;
+            }
+            "--count-optimal" => {
+                count_optimal = true;
+                i += 1;
+            }
+            "--bench" => {
+                bench = true;
+                i += 1;
+            }
+            "--count-explored" => {
+                count_explored = true;
+                i += 1;
+            }
             arg => {
                 if arg.starts_with('-') {
                     eprintln!("error: Invalid option: {}", arg);
@@ -466,7 +1489,7 @@ fn main() {
         }
     }
 
-    if let Some(gen_spec) = &generate_spec {
+    let mut grid = if let Some(gen_spec) = &generate_spec {
         let parts: Vec<&str> = gen_spec.split('x').collect();
         if parts.len() != 2 {
             eprintln!("Error: Invalid format. Use WIDTHxHEIGHT (e.g., 12x8)");
@@ -483,8 +1506,29 @@ fn main() {
             std::process::exit(1);
         });
 
+        if gen_options.min_value > gen_options.max_value {
+            eprintln!("Error: --min-value must not be greater than --max-value");
+            std::process::exit(1);
+        }
+
         println!("Generating {}x{} hexadecimal grid...", width, height);
-        let grid = generate_map(width, height);
+        let grid = if ensure_solvable {
+            let mut attempts = 0;
+            loop {
+                attempts += 1;
+                let candidate = generate_map(width, height, &gen_options);
+                if is_reachable(&candidate) {
+                    println!("Solvable map found after {} attempt(s)", attempts);
+                    break candidate;
+                }
+                if attempts >= retries {
+                    eprintln!("Error: No solvable map found after {} attempts", retries);
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            generate_map(width, height, &gen_options)
+        };
 
         if let Some(output) = &output_path {
             if let Err(e) = save_map(&grid, output) {
@@ -495,37 +1539,267 @@ fn main() {
         }
 
         println!("\nGenerated map:");
-        for row in &grid.cells {
+        for row in grid.rows() {
             let line: Vec<String> = row.iter().map(|&v| format!("{:02X}", v)).collect();
             println!("{}", line.join(" "));
         }
 
-        return;
+        if preview {
+            if legend {
+                print_legend(!no_color, cell_char);
+            }
+            print_rainbow_grid(&grid, !no_color, cell_char);
+        }
+
+        grid
+    } else {
+        let map = map_path.as_ref().unwrap_or_else(|| {
+            eprintln!("Error: Map file required (or use --generate)");
+            std::process::exit(1);
+        });
+
+        let format = map_format.unwrap_or_else(|| MapFormat::detect(map));
+        match parse_map(map, format) {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    grid.wrap = wrap;
+
+    if !relax {
+        for warning in endpoint_warnings(&grid, expected_end) {
+            eprintln!("Warning: {} (use --relax to silence)", warning);
+        }
     }
 
-    let map = map_path.as_ref().unwrap_or_else(|| {
-        eprintln!("Error: Map file required (or use --generate)");
-        std::process::exit(1);
-    });
+    if let Some(route_file) = &show_path {
+        let points = load_path(route_file, &grid).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+        show_imported_path(&grid, &model, &points, cell_char, !no_color);
+        return;
+    }
 
-    let grid = match parse_map(map) {
-        Ok(g) => g,
-        Err(e) => {
+    if let Some(stops) = &waypoints {
+        for &(x, y) in stops {
+            if x >= grid.width || y >= grid.height {
+                eprintln!(
+                    "Error: Waypoint ({},{}) is outside the {}×{} grid",
+                    x, y, grid.width, grid.height
+                );
+                std::process::exit(1);
+            }
+        }
+        let deadline = time_limit.map(|ms| Instant::now() + Duration::from_millis(ms));
+        let route = route_through_waypoints(&grid, &model, stops, deadline).unwrap_or_else(|e| {
             eprintln!("Error: {}", e);
             std::process::exit(1);
+        });
+        if visualize {
+            if legend {
+                print_legend(!no_color, cell_char);
+            }
+            visualize_grid(&grid, Some(&route), None, cell_char, !no_color);
+        } else {
+            let stops_str: Vec<String> = stops
+                .iter()
+                .map(|(x, y)| format!("({},{})", x, y))
+                .collect();
+            println!("Waypoints: {}", stops_str.join(", "));
+            print_path_analysis(&grid, &model, &route, "WAYPOINT");
+        }
+        if let Some(limit) = max_cost {
+            enforce_max_cost(Some(&route), limit, false);
+        }
+        return;
+    }
+
+    if reachable {
+        let (gx, gy) = (grid.width - 1, grid.height - 1);
+        if is_reachable(&grid) {
+            println!("Goal ({},{}) is reachable from (0,0)", gx, gy);
+            return;
+        }
+        println!("Goal ({},{}) is not reachable from (0,0)", gx, gy);
+        std::process::exit(1);
+    }
+
+    if turn_penalty > 0 && bidirectional {
+        eprintln!("Error: --turn-penalty cannot be combined with --bidirectional");
+        std::process::exit(1);
+    }
+
+    if goal_value.is_some() && (turn_penalty > 0 || bidirectional) {
+        eprintln!("Error: --goal-value cannot be combined with --turn-penalty or --bidirectional");
+        std::process::exit(1);
+    }
+
+    if count_optimal && (turn_penalty > 0 || goal_value.is_some()) {
+        eprintln!("Error: --count-optimal cannot be combined with --turn-penalty or --goal-value");
+        std::process::exit(1);
+    }
+
+    let checkpointing = checkpoint_path.is_some() || resume_path.is_some();
+    if checkpointing && (turn_penalty > 0 || bidirectional || goal_value.is_some()) {
+        eprintln!("Error: --checkpoint and --resume only apply to the standard minimum search");
+        std::process::exit(1);
+    }
+
+    if bench && (turn_penalty > 0 || goal_value.is_some()) {
+        eprintln!("Error: --bench cannot be combined with --turn-penalty or --goal-value");
+        std::process::exit(1);
+    }
+
+    if bench {
+        let deadline = time_limit.map(|ms| Instant::now() + Duration::from_millis(ms));
+        run_benchmark(&grid, &model, deadline);
+        return;
+    }
+
+    if animate || events {
+        animate_pathfinding(&grid, &model, events);
+        if let Some(limit) = max_cost {
+            let outcome = dijkstra_min(&grid, &model, None, None);
+            enforce_max_cost(outcome.result.as_ref(), limit, events);
+        }
+        return;
+    }
+
+    let deadline = time_limit.map(|ms| Instant::now() + Duration::from_millis(ms));
+    let mut meeting_point = None;
+    let min_outcome = if turn_penalty > 0 {
+        dijkstra_min_turns(&grid, &model, turn_penalty, deadline)
+    } else if bidirectional {
+        let (outcome, meet) = dijkstra_bidirectional(&grid, &model, deadline);
+        meeting_point = meet;
+        outcome
+    } else if checkpointing {
+        let save_to = checkpoint_path.as_ref().or(resume_path.as_ref()).unwrap();
+        run_checkpointed(&grid, &model, resume_path.as_deref(), save_to, deadline)
+    } else if heatmap {
+        dijkstra_min_traced(&grid, &model, goal_value, deadline)
+    } else {
+        dijkstra_min(&grid, &model, goal_value, deadline)
+    };
+    exit_if_timed_out(&min_outcome, "minimum");
+    let max_outcome = if goal_value.is_some() {
+        SearchOutcome {
+            result: None,
+            explored: 0,
+            timed_out: false,
+            diagnostics: None,
         }
+    } else if let Some(floor) = prune_below {
+        longest_path_exact(&grid, &model, floor, deadline)
+    } else {
+        dijkstra_max(&grid, &model, deadline)
     };
+    exit_if_timed_out(&max_outcome, "maximum");
 
-    if animate {
-        animate_pathfinding(&grid);
+    let (min_explored, max_explored) = (min_outcome.explored, max_outcome.explored);
+    let min_result = min_outcome.result;
+    if (diagnostics || heatmap) && min_outcome.diagnostics.is_none() {
+        eprintln!(
+            "Warning: --diagnostics and --heatmap are only available for the standard minimum search"
+        );
+    }
+    let settle_order = if heatmap {
+        min_outcome
+            .diagnostics
+            .as_ref()
+            .and_then(|d| d.settle_order.clone())
+    } else {
+        None
+    };
+    let search_diagnostics = if diagnostics {
+        min_outcome.diagnostics
+    } else {
+        None
+    };
+    let max_result = max_outcome.result;
+    if verify {
+        if let Some(ref min) = min_result {
+            match verify_min_path(&grid, &model, min, turn_penalty, goal_value) {
+                Ok(m) => eprintln!("Verify: OK, {}", m),
+                Err(m) => eprintln!("Warning: verification failed, {}", m),
+            }
+        }
+    }
+    if cost_only {
+        let min = min_result.unwrap_or_else(|| std::process::exit(1));
+        if both {
+            let max = max_result.unwrap_or_else(|| std::process::exit(1));
+            println!("{} {}", min.total_cost, max.total_cost);
+        } else {
+            println!("{}", min.total_cost);
+        }
+        if let Some(limit) = max_cost {
+            enforce_max_cost(Some(&min), limit, true);
+        }
         return;
     }
 
-    let min_result = dijkstra_min(&grid);
-    let max_result = dijkstra_max(&grid);
+    let explored = if count_explored {
+        Some((min_explored, max_explored))
+    } else {
+        None
+    };
 
-    if visualize {
-        visualize_grid(&grid, min_result.as_ref(), max_result.as_ref());
+    let stats = if show_stats {
+        Some(compute_stats(&grid, &model, goal_value))
+    } else {
+        None
+    };
+
+    if json {
+        print_json(
+            &grid,
+            min_result.as_ref(),
+            max_result.as_ref(),
+            stats.as_ref(),
+            meeting_point,
+            explored,
+            search_diagnostics.as_ref(),
+        );
+        if let Some(limit) = max_cost {
+            enforce_max_cost(min_result.as_ref(), limit, true);
+        }
+        return;
+    }
+
+    if let Some((x, y)) = meeting_point {
+        println!("Bidirectional search met at ({},{})", x, y);
+    }
+
+    if let Some(value) = goal_value {
+        match min_result.as_ref().and_then(|m| m.path.last()) {
+            Some(&(x, y)) => println!("Goal 0x{:02X} reached at ({},{})", value, x, y),
+            None => println!("No cell with value 0x{:02X} is reachable", value),
+        }
+    }
+
+    if path_only {
+        if let Some(ref min) = min_result {
+            print_path_only(&grid, min, !no_color);
+        }
+    } else if let Some(ref order) = settle_order {
+        print_heatmap(&grid, order, !no_color);
+    } else if visualize {
+        if legend {
+            print_legend(!no_color, cell_char);
+        }
+        visualize_grid(
+            &grid,
+            min_result.as_ref(),
+            max_result.as_ref(),
+            cell_char,
+            !no_color,
+        );
     } else {
         println!("Analyzing hexadecimal grid...");
         println!("Grid size: {}×{}", grid.width, grid.height);
@@ -538,11 +1812,92 @@ fn main() {
         );
 
         if let Some(ref min) = min_result {
-            print_path_analysis(&grid, min, "MINIMUM");
+            print_path_analysis(&grid, &model, min, "MINIMUM");
+            if turn_penalty > 0 {
+                let turns = count_turns(&min.path);
+                println!(
+                    "  Includes {} turns × {} = {} turn penalty",
+                    turns,
+                    turn_penalty,
+                    (turns as u32).saturating_mul(turn_penalty)
+                );
+            }
         }
 
         if let Some(ref max) = max_result {
-            print_path_analysis(&grid, max, "MAXIMUM");
+            print_path_analysis(&grid, &model, max, "MAXIMUM");
+        }
+    }
+
+    if compare {
+        if let (Some(min), Some(max)) = (&min_result, &max_result) {
+            print_comparison(min, max);
+        }
+    }
+
+    if let Some(ref stats) = stats {
+        print_stats(stats);
+    }
+
+    if let Some((min, max)) = explored {
+        println!(
+            "\nExplored nodes: {} (minimum search), {} (maximum search)",
+            min, max
+        );
+    }
+
+    if count_optimal {
+        match count_optimal_paths(&grid, &model) {
+            OptimalCount::Paths(u64::MAX) => {
+                println!("\nOptimal paths: at least {} (count saturated)", u64::MAX)
+            }
+            OptimalCount::Paths(n) => println!("\nOptimal paths: {}", n),
+            OptimalCount::Unbounded => {
+                println!("\nOptimal paths: unbounded (a zero-cost loop lies on an optimal route)")
+            }
+            OptimalCount::Unreachable => println!("\nOptimal paths: 0 (goal unreachable)"),
+        }
+    }
+
+    if let Some(ref diag) = search_diagnostics {
+        print_diagnostics(diag);
+    }
+
+    if let Some(limit) = max_cost {
+        enforce_max_cost(min_result.as_ref(), limit, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corridor(width: usize, height: usize) -> Grid {
+        let mut grid = Grid::new(width, height, vec![0xFF; width * height]);
+        let mut rng = SimplePrng {
+            state: 0x9E3779B97F4A7C15,
+        };
+        carve_corridor(&mut grid, &mut rng, 0x00, 0x10);
+        grid
+    }
+
+    #[test]
+    fn corridor_on_a_single_cell_leaves_it_alone() {
+        assert_eq!(corridor(1, 1).get(0, 0), 0xFF);
+    }
+
+    #[test]
+    fn corridor_on_a_single_row_or_column_carves_between_the_ends() {
+        for (width, height) in [(1, 2), (1, 6), (6, 1)] {
+            let grid = corridor(width, height);
+            let cells: Vec<u8> = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| grid.get(x, y))
+                .collect();
+            let (first, rest) = cells.split_first().unwrap();
+            let (last, middle) = rest.split_last().unwrap();
+            assert_eq!((*first, *last), (0xFF, 0xFF));
+            assert!(middle.iter().all(|&v| v <= 0x10), "{}x{}", width, height);
         }
     }
 }